@@ -0,0 +1,69 @@
+//! Measures `Router::dispatch` against route tables of increasing size.
+//!
+//! Run with `cargo bench --bench router`. The time per dispatch should stay roughly constant as the
+//! number of registered routes grows, since matching only depends on the length of the path.
+
+use std::{hint::black_box, time::Instant};
+
+use verglas::{
+    http::{
+        request::Request,
+        response::{Response, ResponseBuilder},
+        Method,
+    },
+    router::{Route, Router, RouterBuilder},
+};
+
+const ITERATIONS: u32 = 100_000;
+
+fn handler(_request: &Request) -> Response {
+    ResponseBuilder::new().empty().build()
+}
+
+fn router(size: usize) -> Router {
+    (0..size)
+        .fold(RouterBuilder::new(), |builder, index| {
+            builder
+                .with_route(Route::new(
+                    &format!("/resource{index}"),
+                    Method::Get,
+                    handler,
+                ))
+                .with_route(Route::new(
+                    &format!("/resource{index}/:id/items/:item"),
+                    Method::Get,
+                    handler,
+                ))
+        })
+        .build()
+}
+
+fn bench(router: &Router, path: &str) -> f64 {
+    let mut request =
+        Request::try_from(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_str())
+            .unwrap();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(router.dispatch(black_box(&mut request)));
+    }
+
+    start.elapsed().as_nanos() as f64 / ITERATIONS as f64
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>16} {:>16}",
+        "routes", "static (ns)", "params (ns)"
+    );
+
+    for size in [10, 100, 1_000, 10_000] {
+        let router = router(size);
+        let last = size - 1;
+
+        let static_path = bench(&router, &format!("/resource{last}"));
+        let param_path = bench(&router, &format!("/resource{last}/42/items/7"));
+
+        println!("{:>8} {:>16.1} {:>16.1}", size * 2, static_path, param_path);
+    }
+}
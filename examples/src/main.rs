@@ -1,52 +1,79 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
 use verglas::{
-    core::Server,
+    core::ServerBuilder,
     http::{cookie::CookieBuilder, response::ResponseBuilder, Method},
-    router::{Route, RouterBuilder},
+    router::{middleware::from_fn, Route, RouterBuilder},
 };
 
 fn main() {
+    let visits = Arc::new(AtomicUsize::new(0));
+
     let router = RouterBuilder::new()
-        .with_route(Route {
-            path: "/".to_string(),
-            method: Method::Get,
-            handler: |request| {
-                let cookie_counter = CookieBuilder::new().with_key("counter");
-
-                let cookie_counter = match request.get_cookie("counter") {
-                    Some(cookie) => {
-                        let counter = match cookie.value.parse::<u32>() {
-                            Ok(counter) => counter + 1,
-                            Err(_) => 1,
-                        };
-
-                        cookie_counter.with_value(counter.to_string().as_str())
-                    }
-                    None => cookie_counter.with_value("1"),
-                };
-
-                let response = ResponseBuilder::new()
-                    .empty()
-                    .with_body("Hello, GET!")
-                    .with_cookies(vec![cookie_counter.build()])
-                    .build();
-
-                println!("{}", String::from(&response));
-
-                response
-            },
-        })
-        .with_route(Route {
-            path: "/".to_string(),
-            method: Method::Post,
-            handler: |_request| {
-                ResponseBuilder::new()
-                    .empty()
-                    .with_body("Hello, POST!")
-                    .build()
-            },
-        })
+        .with_middleware(from_fn(|request, next| {
+            let response = next.run(request);
+            println!(
+                "{} {} -> {}",
+                request.method.as_str(),
+                request.uri.path,
+                response.status_code()
+            );
+            response
+        }))
+        .with_route(Route::new("/", Method::Get, |request| {
+            let cookie_counter = CookieBuilder::new().with_key("counter");
+
+            let cookie_counter = match request.get_cookie("counter") {
+                Some(cookie) => {
+                    let counter = match cookie.value.parse::<u32>() {
+                        Ok(counter) => counter + 1,
+                        Err(_) => 1,
+                    };
+
+                    cookie_counter.with_value(counter.to_string().as_str())
+                }
+                None => cookie_counter.with_value("1"),
+            };
+
+            let response = ResponseBuilder::new()
+                .empty()
+                .with_body("Hello, GET!")
+                .with_cookies(vec![cookie_counter.build()])
+                .build();
+
+            println!("{}", String::from(&response));
+
+            response
+        }))
+        .with_route(Route::new("/", Method::Post, |_request| "Hello, POST!"))
+        .with_route(Route::new("/users/:id", Method::Get, |request| {
+            let id = request.param("id").unwrap();
+
+            ResponseBuilder::new()
+                .empty()
+                .with_body(format!("Hello, user {id}!").as_str())
+                .build()
+        }))
+        .with_route(Route::new("/visits", Method::Get, move |_request| {
+            let visits = visits.fetch_add(1, Ordering::Relaxed) + 1;
+
+            ResponseBuilder::new()
+                .empty()
+                .with_body(format!("{visits} visits so far").as_str())
+                .build()
+        }))
         .build();
 
-    let server = Server::new(vec!["127.0.0.1:80".parse().unwrap()], router);
-    server.run();
+    let server = ServerBuilder::new(vec!["127.0.0.1:80".parse().unwrap()], router)
+        .with_debug(true)
+        .try_build()
+        .and_then(|server| server.run());
+
+    if let Err(e) = server {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
 }
@@ -1,89 +1,921 @@
+#[cfg(feature = "tokio")]
+mod async_server;
+pub mod error;
+mod listener;
+mod pool;
+mod shutdown;
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
 use std::{
-    io::Write,
-    net::{SocketAddr, TcpListener, TcpStream},
-    sync::Arc,
-    thread,
+    ops::Range,
+    os::unix::{
+        io::{OwnedFd, RawFd},
+        net::UnixListener,
+    },
+    path::{Path, PathBuf},
 };
 
 use crate::{
-    http::{request::Request, response::ResponseBuilder},
-    router::Router,
+    http::{
+        request::{Limits, Request, CONNECTION_CLOSED},
+        response::{Response, ResponseBuilder},
+        Version,
+    },
+    router::{error::RequestError, Router},
 };
 
-#[derive(Debug)]
-struct Connection {
-    thread: std::thread::JoinHandle<()>,
+use self::{
+    error::ServerError,
+    listener::{Listener, Stream, Timed},
+    pool::ThreadPool,
+    shutdown::ShutdownState,
+};
+
+pub use self::shutdown::ShutdownHandle;
+
+#[cfg(feature = "tokio")]
+pub use self::async_server::AsyncServer;
+#[cfg(feature = "tokio")]
+pub(crate) use self::async_server::{block_on, catch_unwind};
+
+/// The settings of a [`Server`], see [`ServerBuilder`] for their meaning and defaults.
+struct Config {
+    read_timeout: Duration,
+    write_timeout: Duration,
+    idle_timeout: Duration,
+    limits: Limits,
+    max_connections: usize,
+    max_requests_per_connection: usize,
+    workers: usize,
+    queue_size: usize,
+    shutdown_timeout: Duration,
+    debug: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(5),
+            limits: Limits::default(),
+            max_connections: 1024,
+            max_requests_per_connection: 1000,
+            workers: 32,
+            queue_size: 64,
+            shutdown_timeout: Duration::from_secs(30),
+            debug: false,
+        }
+    }
+}
+
+/// Configures a [`Server`] before binding it.
+///
+/// Every setting has a conservative default: 30 seconds to receive a request or send a response,
+/// 5 seconds of keep-alive between requests, 8 KiB of headers, 1 MiB of body, 1024 concurrent
+/// connections, 1000 requests per connection, 32 workers and a queue of 64 connections.
+pub struct ServerBuilder {
+    address: Address,
+    router: Router,
+    config: Config,
+}
+
+/// Where a [`ServerBuilder`] binds its listener.
+enum Address {
+    Tcp(Vec<SocketAddr>),
+    /// The socket path and its permissions.
+    #[cfg(unix)]
+    Unix(PathBuf, Option<u32>),
+    /// A listener opened by the caller.
+    Listener(io::Result<Listener>),
+    #[cfg(unix)]
+    Fd(OwnedFd),
+    /// The descriptors passed by systemd.
+    #[cfg(unix)]
+    Systemd(io::Result<Range<RawFd>>),
+}
+
+impl ServerBuilder {
+    /// Configures a server listening on the first of the TCP addresses available.
+    pub fn new(addresses: Vec<SocketAddr>, router: Router) -> Self {
+        Self {
+            address: Address::Tcp(addresses),
+            router,
+            config: Config::default(),
+        }
+    }
+
+    /// Configures a server listening on a Unix domain socket at the given path.
+    ///
+    /// A socket left at the path by a process which is no longer listening on it is replaced,
+    /// and the socket is removed once the server stops. The peer of each request is identified by
+    /// its [`Credentials`](crate::http::Credentials) where the platform provides them.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P, router: Router) -> Self {
+        Self {
+            address: Address::Unix(path.as_ref().to_path_buf(), None),
+            router,
+            config: Config::default(),
+        }
+    }
+
+    /// Configures a server accepting connections from a TCP listener opened by the caller, e.g.
+    /// inherited from a previous process during a deployment.
+    pub fn from_listener(listener: TcpListener, router: Router) -> Self {
+        Self {
+            address: Address::Listener(Listener::from_tcp(listener)),
+            router,
+            config: Config::default(),
+        }
+    }
+
+    /// Configures a server accepting connections from a Unix domain socket listener opened by the
+    /// caller. Its socket file is left in place once the server stops.
+    #[cfg(unix)]
+    pub fn from_unix_listener(listener: UnixListener, router: Router) -> Self {
+        Self {
+            address: Address::Listener(Listener::from_unix(listener)),
+            router,
+            config: Config::default(),
+        }
+    }
+
+    /// Configures a server accepting connections from a listening socket inherited as a file
+    /// descriptor, either TCP or Unix domain.
+    #[cfg(unix)]
+    pub fn from_fd(fd: OwnedFd, router: Router) -> Self {
+        Self {
+            address: Address::Fd(fd),
+            router,
+            config: Config::default(),
+        }
+    }
+
+    /// Configures a server accepting connections from the socket passed by systemd socket
+    /// activation through the `LISTEN_PID` and `LISTEN_FDS` variables, which lets systemd bind
+    /// privileged ports such as 80 and keep the socket open across restarts.
+    ///
+    /// Only the first socket is served, the others being closed. Building the server fails with
+    /// a [`ServerError::Bind`] of kind [`NotFound`](io::ErrorKind::NotFound) when the process
+    /// was not socket activated.
+    ///
+    /// The variables are left in the environment, where child processes ignore them since they
+    /// name the PID of this process, see [`ServerBuilder::systemd_unset_env`] to remove them.
+    #[cfg(unix)]
+    pub fn systemd(router: Router) -> Self {
+        Self {
+            address: Address::Systemd(listener::systemd_fds()),
+            router,
+            config: Config::default(),
+        }
+    }
+
+    /// Configures a server like [`ServerBuilder::systemd`], then removes the variables of socket
+    /// activation from the environment.
+    ///
+    /// # Safety
+    ///
+    /// Modifying the environment races with other threads reading it, so this must be called
+    /// before any thread is spawned, e.g. at the start of `main`.
+    #[cfg(unix)]
+    pub unsafe fn systemd_unset_env(router: Router) -> Self {
+        let builder = Self::systemd(router);
+        for name in listener::LISTEN_VARS {
+            std::env::remove_var(name);
+        }
+
+        builder
+    }
+
+    /// Sets the permissions of the Unix domain socket, e.g. `0o660` to let only the owner and
+    /// group of the server connect, instead of the ones derived from the umask. They apply from the
+    /// first connection, the socket being bound in a private directory next to its path before
+    /// being linked at it. Ignored for TCP.
+    #[cfg(unix)]
+    pub fn with_socket_mode(mut self, mode: u32) -> Self {
+        if let Address::Unix(_, socket_mode) = &mut self.address {
+            *socket_mode = Some(mode);
+        }
+        self
+    }
+
+    /// Sets how long receiving a request may take, from its first byte to the end of its body,
+    /// before the connection is closed, zero disabling the timeout.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.read_timeout = timeout;
+        self
+    }
+
+    /// Sets how long sending a response may take before the connection is closed, zero disabling
+    /// the timeout.
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.config.write_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a keep-alive connection may wait for its next request before being closed,
+    /// zero disabling the timeout.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.idle_timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum size of the request line and headers. Larger requests are answered with a
+    /// 431 and the connection is closed.
+    pub fn with_max_header_bytes(mut self, max_header_bytes: usize) -> Self {
+        self.config.limits.max_header_bytes = max_header_bytes;
+        self
+    }
+
+    /// Sets the maximum size of a request body. Larger requests are answered with a 413 and the
+    /// connection is closed.
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.config.limits.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Sets the maximum number of open connections, including the ones waiting for a worker.
+    /// Connections accepted beyond it are answered with a 503 and closed.
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.config.max_connections = max_connections;
+        self
+    }
+
+    /// Sets the number of requests served on a connection before it is closed.
+    pub fn with_max_requests_per_connection(mut self, max_requests: usize) -> Self {
+        self.config.max_requests_per_connection = max_requests;
+        self
+    }
+
+    /// Sets the number of worker threads, each serving one connection at a time.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.config.workers = workers;
+        self
+    }
+
+    /// Sets the number of accepted connections waiting for a free worker. Connections accepted
+    /// while the queue is full are answered with a 503 and closed.
+    pub fn with_queue_size(mut self, queue_size: usize) -> Self {
+        self.config.queue_size = queue_size;
+        self
+    }
+
+    /// Sets how long active connections may take to finish their current request on shutdown
+    /// before being closed.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.config.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Prints the routing table when the server starts running.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.config.debug = debug;
+        self
+    }
+
+    /// Binds the server, panicking if none of the addresses can be bound.
+    ///
+    /// See [`ServerBuilder::try_build`] for a fallible version.
+    pub fn build(self) -> Server {
+        match self.try_build() {
+            Ok(server) => server,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Binds the server to the first address available.
+    pub fn try_build(self) -> Result<Server, ServerError> {
+        let listener = self.address.bind()?;
+        let endpoint = listener.endpoint().map_err(ServerError::Bind)?;
+
+        Ok(Server {
+            listener,
+            router: Arc::new(self.router),
+            config: Arc::new(self.config),
+            shutdown: ShutdownState::new(endpoint),
+            connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+}
+
+impl Address {
+    fn bind(self) -> Result<Listener, ServerError> {
+        match self {
+            Address::Tcp(addresses) => Listener::bind_tcp(&addresses),
+            #[cfg(unix)]
+            Address::Unix(path, mode) => Listener::bind_unix(&path, mode),
+            Address::Listener(listener) => listener,
+            #[cfg(unix)]
+            Address::Fd(fd) => Listener::from_fd(fd),
+            #[cfg(unix)]
+            Address::Systemd(fds) => fds.and_then(Listener::from_systemd),
+        }
+        .map_err(ServerError::Bind)
+    }
 }
 
 pub struct Server {
-    listener: TcpListener,
-    connections: Vec<Connection>,
+    listener: Listener,
     router: Arc<Router>,
+    config: Arc<Config>,
+    shutdown: Arc<ShutdownState>,
+    connections: Arc<AtomicUsize>,
+}
+
+/// Counts an open connection until dropped.
+struct Counted(Arc<AtomicUsize>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl Server {
+    /// Binds a server with the default settings of [`ServerBuilder`], panicking if none of the
+    /// addresses can be bound.
     pub fn new(addresses: Vec<SocketAddr>, router: Router) -> Self {
-        Self {
-            listener: TcpListener::bind(&addresses[..]).unwrap(),
-            connections: Vec::new(),
-            router: Arc::new(router),
-        }
+        ServerBuilder::new(addresses, router).build()
+    }
+
+    /// Binds a server with the default settings of [`ServerBuilder`].
+    pub fn bind(addresses: Vec<SocketAddr>, router: Router) -> Result<Self, ServerError> {
+        ServerBuilder::new(addresses, router).try_build()
+    }
+
+    /// Binds a server on a Unix domain socket with the default settings of [`ServerBuilder`].
+    #[cfg(unix)]
+    pub fn bind_unix<P: AsRef<Path>>(path: P, router: Router) -> Result<Self, ServerError> {
+        ServerBuilder::unix(path, router).try_build()
+    }
+
+    /// Returns the address the server is listening on, `None` for a Unix domain socket.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr()
     }
 
-    pub fn run(mut self) {
-        for stream in self.listener.incoming() {
+    /// Returns the path of the Unix domain socket the server is listening on.
+    #[cfg(unix)]
+    pub fn local_path(&self) -> Option<&Path> {
+        self.listener.local_path()
+    }
+
+    /// Returns a handle stopping the server gracefully, see [`ShutdownHandle::shutdown`].
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.handle()
+    }
+
+    /// Serves connections until the server is stopped through a [`ShutdownHandle`].
+    ///
+    /// On shutdown, stops accepting connections, closes the idle ones and waits for the others to
+    /// finish their current request, up to the shutdown timeout, before returning. Connections
+    /// still active at the deadline are closed, and handlers still running are left to finish in
+    /// the background.
+    ///
+    /// Failures of a single connection are reported on the standard error without stopping the
+    /// server.
+    pub fn run(self) -> Result<(), ServerError> {
+        if self.config.debug {
+            print!("{}", self.router);
+        }
+
+        let pool = ThreadPool::new(self.config.workers, self.config.queue_size)
+            .map_err(ServerError::Spawn)?;
+
+        loop {
+            let stream = self.listener.accept();
+            if self.shutdown.is_stopping() {
+                break;
+            }
+
             match stream {
                 Ok(stream) => {
+                    if self.connections.fetch_add(1, Ordering::SeqCst)
+                        >= self.config.max_connections
+                    {
+                        self.connections.fetch_sub(1, Ordering::SeqCst);
+                        Self::reject(stream);
+                        continue;
+                    }
+
+                    let counted = Counted(self.connections.clone());
                     let router = self.router.clone();
+                    let config = self.config.clone();
+                    let shutdown = self.shutdown.clone();
+                    let overflow = stream.try_clone();
+
+                    let job = move || {
+                        let _counted = counted;
+                        if let Err(e) = Self::handle_connection(&router, &config, &shutdown, stream)
+                        {
+                            eprintln!("Connection failed: {e}");
+                        }
+                    };
 
-                    self.connections.push(Connection {
-                        thread: thread::spawn(move || Self::handle_connection(router, stream)),
-                    });
+                    if pool.try_execute(job).is_err() {
+                        if let Ok(stream) = overflow {
+                            Self::reject(stream);
+                        }
+                    }
                 }
                 Err(e) => {
                     eprintln!("Failed to establish connection: {e}");
                     continue;
                 }
             }
-
-            self.connections
-                .retain(|connection| !connection.thread.is_finished());
         }
+
+        let deadline = Instant::now() + self.config.shutdown_timeout;
+        self.shutdown.drain(deadline);
+        pool.shutdown(deadline);
+
+        Ok(())
     }
 
-    fn handle_connection(router: Arc<Router>, mut stream: TcpStream) {
-        println!(
-            "Connection established with {}!",
-            stream.peer_addr().unwrap()
-        );
+    /// Answers a connection no worker can serve with a 503, then closes it.
+    fn reject(stream: Stream) {
+        let _ = (&stream).write_all(String::from(&unavailable()).as_bytes());
+    }
+
+    fn handle_connection(
+        router: &Router,
+        config: &Config,
+        shutdown: &Arc<ShutdownState>,
+        stream: Stream,
+    ) -> io::Result<()> {
+        let Some(connection) = shutdown.track(&stream) else {
+            return Ok(());
+        };
+
+        let peer = stream.peer()?;
+        println!("Connection established with {peer}!");
+
+        let mut reader = BufReader::new(Timed::new(&stream, config.idle_timeout));
+        let mut served = 0;
 
         loop {
-            match Request::try_from(&mut stream) {
-                Ok(request) => {
-                    let response = router.dispatch(&request);
+            // Waits for the first byte of the next request within the keep-alive timeout, the
+            // connection being closed by the client or timing out otherwise
+            reader.get_mut().restart(config.idle_timeout);
+            if !matches!(reader.fill_buf(), Ok(buffer) if !buffer.is_empty()) {
+                break;
+            }
+            // A request partially received is left to complete on shutdown
+            if !connection.busy() {
+                break;
+            }
+            reader.get_mut().restart(config.read_timeout);
 
-                    stream
-                        .write_all(String::from(&response).as_bytes())
-                        .unwrap();
+            let (response, keep_alive) = match Request::read_from(&mut reader, &config.limits) {
+                Ok(mut request) => {
+                    served += 1;
+                    request.peer = Some(peer.clone());
+                    let response = router.dispatch(&mut request);
 
-                    match request.get_header("Connection") {
-                        Some(connection) if connection == "close" => break,
-                        _ => {}
-                    }
+                    persist(response, Some(&request), served, config, shutdown)
                 }
+                Err(CONNECTION_CLOSED) => break,
                 Err(e) => {
-                    let response = ResponseBuilder::new()
-                        .internal_server_error()
-                        .with_body(e)
-                        .build();
-
-                    stream
-                        .write_all(String::from(&response).as_bytes())
-                        .unwrap();
+                    let response = router.handle_error(&RequestError::Parse(e));
+                    persist(response, None, served, config, shutdown)
                 }
+            };
+
+            Timed::new(&stream, config.write_timeout)
+                .write_all(String::from(&response).as_bytes())?;
+
+            if !keep_alive || !connection.idle() {
+                break;
             }
         }
 
         println!("Connection closed!");
+        Ok(())
+    }
+}
+
+/// The response sent to connections accepted while the server is saturated.
+fn unavailable() -> Response {
+    ResponseBuilder::new()
+        .with_status_code(503)
+        .with_header("Connection", "close")
+        .build()
+}
+
+/// Decides whether the connection stays open after the response to the request, `None` standing
+/// for a request which could not be parsed, and advertises the decision in its `Connection`
+/// header.
+fn persist(
+    response: Response,
+    request: Option<&Request>,
+    served: usize,
+    config: &Config,
+    shutdown: &ShutdownState,
+) -> (Response, bool) {
+    // After a parse error the stream cannot be resynchronized with the start of the next request
+    let Some(request) = request else {
+        return (response.with_header("Connection", "close"), false);
+    };
+
+    let keep_alive = request.keep_alive()
+        && !response
+            .get_header("Connection")
+            .is_some_and(|value| value.eq_ignore_ascii_case("close"))
+        && served < config.max_requests_per_connection
+        && !shutdown.is_stopping();
+
+    let connection = match (keep_alive, request.version) {
+        (false, _) => "close",
+        (true, Version::Http1_0) => "keep-alive",
+        (true, Version::Http1_1) => return (response, true),
+    };
+
+    let response = response
+        .without_header("Connection")
+        .with_header("Connection", connection);
+
+    (response, keep_alive)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+        sync::mpsc,
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        http::Method,
+        router::{Route, RouterBuilder},
+    };
+
+    use super::{error::ServerError, Server, ServerBuilder, ShutdownHandle};
+
+    /// Runs the server on another thread until shut down through the returned handle.
+    fn serve(server: Server) -> (SocketAddr, ShutdownHandle, JoinHandle<()>) {
+        let address = server.local_addr().unwrap();
+        let handle = server.shutdown_handle();
+        let running = thread::spawn(move || server.run().unwrap());
+
+        (address, handle, running)
+    }
+
+    /// Sends the raw request on a new connection and reads the response until it is closed.
+    fn send(address: SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn graceful_shutdown() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/slow", Method::Get, |_request| {
+                thread::sleep(Duration::from_millis(200));
+                "done"
+            }))
+            .build();
+
+        let server = Server::new(vec!["127.0.0.1:0".parse().unwrap()], router);
+        let address = server.local_addr().unwrap();
+        let handle = server.shutdown_handle();
+        let state = server.shutdown.clone();
+        let running = thread::spawn(move || server.run().unwrap());
+
+        let mut idle = TcpStream::connect(address).unwrap();
+        let mut active = TcpStream::connect(address).unwrap();
+        active
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut partial = TcpStream::connect(address).unwrap();
+        partial.write_all(b"GET /slow HTTP/1.1\r\nHo").unwrap();
+
+        // Shuts down once both requests started arriving
+        while state.count() != (3, 2) {
+            thread::yield_now();
+        }
+        handle.shutdown();
+
+        partial.write_all(b"st: localhost\r\n\r\n").unwrap();
+        running.join().unwrap();
+
+        for mut stream in [active, partial] {
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK"));
+            assert!(response.contains("Connection: close"));
+            assert!(response.ends_with("done"));
+        }
+
+        let mut response = String::new();
+        idle.read_to_string(&mut response).unwrap();
+        assert!(response.is_empty());
+        assert!(TcpStream::connect(address).is_err());
+    }
+
+    #[test]
+    fn shutdown_timeout() {
+        let (started, start) = mpsc::channel();
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/stuck", Method::Get, move |_request| {
+                started.send(()).unwrap();
+                thread::sleep(Duration::from_secs(3));
+                "done"
+            }))
+            .build();
+
+        let server = ServerBuilder::new(vec!["127.0.0.1:0".parse().unwrap()], router)
+            .with_shutdown_timeout(Duration::from_millis(100))
+            .build();
+        let (address, handle, running) = serve(server);
+
+        let mut stuck = TcpStream::connect(address).unwrap();
+        stuck
+            .write_all(b"GET /stuck HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        start.recv().unwrap();
+
+        let stopping = Instant::now();
+        handle.shutdown();
+        running.join().unwrap();
+        assert!(stopping.elapsed() < Duration::from_secs(2));
+
+        // The connection is closed at the deadline, without a response
+        let mut response = String::new();
+        let _ = stuck.read_to_string(&mut response);
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Post, |_request| "ok"))
+            .build();
+
+        let server = ServerBuilder::new(vec!["127.0.0.1:0".parse().unwrap()], router)
+            .with_max_body_bytes(4)
+            .with_max_requests_per_connection(1)
+            .with_read_timeout(Duration::from_millis(300))
+            .build();
+        let (address, handle, running) = serve(server);
+
+        let request = b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi";
+        let twice = [&request[..], &request[..]].concat();
+        let response = send(address, &twice);
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(response.matches("HTTP/1.1").count(), 1);
+
+        let response = send(
+            address,
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello",
+        );
+        assert!(response.starts_with("HTTP/1.1 413 Content Too Large"));
+
+        // Trickling the headers byte by byte does not extend the time allowed for the request
+        let mut slow = TcpStream::connect(address).unwrap();
+        slow.write_all(b"POST / HTTP/1.1\r\nX-Slow: ").unwrap();
+        let started = Instant::now();
+        let mut trickle = slow.try_clone().unwrap();
+        let trickling = thread::spawn(move || {
+            for _ in 0..40 {
+                if trickle.write_all(b"a").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+
+        let mut response = String::new();
+        let _ = slow.read_to_string(&mut response);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(response.is_empty());
+        trickling.join().unwrap();
+
+        handle.shutdown();
+        running.join().unwrap();
+    }
+
+    #[test]
+    fn bind_busy_address() {
+        let router = || RouterBuilder::new().build();
+
+        let server = Server::bind(vec!["127.0.0.1:0".parse().unwrap()], router()).unwrap();
+        let busy = Server::bind(vec![server.local_addr().unwrap()], router());
+        assert!(matches!(busy, Err(ServerError::Bind(_))));
+    }
+
+    #[test]
+    fn persistent_connections() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Get, |_request| "ok"))
+            .build();
+
+        let server = Server::new(vec!["127.0.0.1:0".parse().unwrap()], router);
+        let (address, handle, running) = serve(server);
+
+        // Pipelined requests are all answered, until the client asks to close
+        let response = send(
+            address,
+            b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n\
+              GET / HTTP/1.1\r\nHost: localhost\r\n\r\n\
+              GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 3);
+        assert_eq!(response.matches("Connection: close").count(), 1);
+
+        let response = send(address, b"GET / HTTP/1.0\r\n\r\nGET / HTTP/1.0\r\n\r\n");
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(response.contains("Connection: close"));
+
+        let response = send(address, b"BREW / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert_eq!(response.matches("HTTP/1.1").count(), 1);
+        assert!(response.contains("Connection: close"));
+
+        handle.shutdown();
+        running.join().unwrap();
+    }
+
+    #[test]
+    fn pipelined_bodies() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Post, |request| {
+                format!(
+                    "public {}",
+                    request.body.as_deref().unwrap_or_default().len()
+                )
+            }))
+            .with_route(Route::new("/admin", Method::Get, |_request| "admin"))
+            .build();
+
+        let server = Server::new(vec!["127.0.0.1:0".parse().unwrap()], router);
+        let (address, handle, running) = serve(server);
+
+        // The body is consumed whatever the case of its header, never served as a request
+        let admin = b"GET /admin HTTP/1.1\r\nhost: localhost\r\n\r\n";
+        let request = [
+            format!(
+                "POST / HTTP/1.1\r\nhost: localhost\r\ncontent-length: {}\r\n\r\n",
+                admin.len()
+            )
+            .as_bytes(),
+            admin,
+            b"POST / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n",
+        ]
+        .concat();
+        let response = send(address, &request);
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(response.contains(&format!("public {}", admin.len())));
+        assert!(response.ends_with("public 0"));
+        assert!(!response.contains("admin"));
+
+        let response = send(
+            address,
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+              0\r\n\r\nGET /admin HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 501 Not Implemented"));
+        assert!(response.contains("Connection: close"));
+        assert!(!response.contains("admin"));
+
+        let response = send(
+            address,
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\ncontent-length: 39\r\n\r\n\
+              GET /admin HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.contains("Connection: close"));
+        assert!(!response.contains("admin"));
+
+        handle.shutdown();
+        running.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        use std::os::unix::{fs::PermissionsExt, net::UnixListener, net::UnixStream};
+
+        use crate::http::Peer;
+
+        let path = std::env::temp_dir().join(format!("verglas-{}.sock", std::process::id()));
+
+        // A socket left over by a previous process
+        drop(UnixListener::bind(&path).unwrap());
+
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Get, |request| {
+                match &request.peer {
+                    Some(Peer::Unix(Some(credentials))) => credentials.uid.to_string(),
+                    _ => "unknown".to_string(),
+                }
+            }))
+            .build();
+
+        let server = ServerBuilder::unix(&path, router)
+            .with_socket_mode(0o600)
+            .build();
+        assert_eq!(server.local_path(), Some(path.as_path()));
+        assert!(server.local_addr().is_none());
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // The private directory the socket was bound in is gone
+        let private = format!(".verglas-{}-", std::process::id());
+        assert!(!std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .any(|entry| entry
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&private)));
+
+        let busy = Server::bind_unix(&path, RouterBuilder::new().build());
+        assert!(matches!(busy, Err(ServerError::Bind(_))));
+
+        let handle = server.shutdown_handle();
+        let running = thread::spawn(move || server.run().unwrap());
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        // SAFETY: getuid has no preconditions
+        let uid = unsafe { libc::getuid() };
+        assert!(response.ends_with(&format!("\r\n\r\n{uid}")));
+
+        handle.shutdown();
+        running.join().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn inherited_listener() {
+        use std::{
+            net::{TcpListener, UdpSocket},
+            os::unix::io::OwnedFd,
+        };
+
+        let router = || {
+            RouterBuilder::new()
+                .with_route(Route::new("/", Method::Get, |_request| "inherited"))
+                .build()
+        };
+        let get = |address| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let servers = [
+            ServerBuilder::from_listener(listener.try_clone().unwrap(), router()).build(),
+            ServerBuilder::from_fd(OwnedFd::from(listener), router()).build(),
+        ];
+
+        for server in servers {
+            let address = server.local_addr().unwrap();
+            let handle = server.shutdown_handle();
+            let running = thread::spawn(move || server.run().unwrap());
+
+            assert!(get(address).ends_with("\r\n\r\ninherited"));
+
+            handle.shutdown();
+            running.join().unwrap();
+        }
+
+        // Descriptors which cannot be accepted on
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        for fd in [OwnedFd::from(udp), OwnedFd::from(stream)] {
+            let server = ServerBuilder::from_fd(fd, router()).try_build();
+            assert!(matches!(server, Err(ServerError::Bind(_))));
+        }
     }
 }
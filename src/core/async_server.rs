@@ -0,0 +1,589 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    io,
+    net::SocketAddr,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, OnceLock,
+    },
+    task::Poll,
+    thread,
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    runtime::{Handle, Runtime},
+    task::JoinSet,
+};
+
+use crate::{
+    http::{
+        request::{Request, CONNECTION_CLOSED, HEADERS_TOO_LARGE},
+        response::Response,
+        Peer,
+    },
+    router::{error::RequestError, Router},
+};
+
+use super::{
+    error::ServerError, listener::Listener, persist, shutdown::ShutdownState, unavailable, Config,
+    Counted, ServerBuilder, ShutdownHandle,
+};
+
+impl ServerBuilder {
+    /// Binds a server running on a tokio runtime, see [`AsyncServer`]. Requires the `tokio`
+    /// feature.
+    ///
+    /// Only TCP addresses are supported.
+    pub fn try_build_async(self) -> Result<AsyncServer, ServerError> {
+        let listener = self.address.bind()?;
+        let endpoint = listener.endpoint().map_err(ServerError::Bind)?;
+
+        let listener = match listener {
+            Listener::Tcp(listener) => listener,
+            #[cfg(unix)]
+            Listener::Unix(..) => {
+                return Err(ServerError::Bind(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "The async server only listens on TCP addresses",
+                )))
+            }
+        };
+        listener.set_nonblocking(true).map_err(ServerError::Bind)?;
+        let address = listener.local_addr().map_err(ServerError::Bind)?;
+
+        Ok(AsyncServer {
+            listener,
+            address,
+            router: Arc::new(self.router),
+            config: Arc::new(self.config),
+            shutdown: ShutdownState::new(endpoint),
+        })
+    }
+}
+
+/// A server multiplexing its connections on the non-blocking sockets of a tokio runtime, so idle
+/// keep-alive connections only cost a task instead of a worker thread.
+///
+/// It serves the same [`Router`] as [`Server`](super::Server). Handlers created with
+/// [`Route::new_async`] are awaited on the task of their connection, while other handlers, which
+/// may block, are dispatched on the blocking thread pool of the runtime. The settings of
+/// [`ServerBuilder`] apply, except the number of workers and the queue size.
+///
+/// [`Route::new_async`]: crate::router::Route::new_async
+pub struct AsyncServer {
+    listener: std::net::TcpListener,
+    address: SocketAddr,
+    router: Arc<Router>,
+    config: Arc<Config>,
+    shutdown: Arc<ShutdownState>,
+}
+
+impl AsyncServer {
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns a handle stopping the server gracefully, see [`ShutdownHandle::shutdown`].
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.handle()
+    }
+
+    /// Serves connections on the current tokio runtime until the server is stopped through a
+    /// [`ShutdownHandle`].
+    ///
+    /// On shutdown, stops accepting connections, closes the idle ones and waits for the others to
+    /// finish their current request, up to the shutdown timeout, before closing the remaining
+    /// ones and returning.
+    pub async fn run(self) -> Result<(), ServerError> {
+        if self.config.debug {
+            print!("{}", self.router);
+        }
+
+        let listener = TcpListener::from_std(self.listener).map_err(ServerError::Bind)?;
+        let connections = Arc::new(AtomicUsize::new(0));
+        let mut tasks = JoinSet::new();
+
+        loop {
+            let stream = tokio::select! {
+                stream = listener.accept() => stream,
+                () = self.shutdown.stopped() => break,
+            };
+            while tasks.try_join_next().is_some() {}
+
+            let (mut stream, peer) = match stream {
+                Ok((stream, address)) => (stream, Peer::Tcp(address)),
+                Err(e) => {
+                    eprintln!("Failed to establish connection: {e}");
+                    continue;
+                }
+            };
+
+            if connections.fetch_add(1, Ordering::SeqCst) >= self.config.max_connections {
+                connections.fetch_sub(1, Ordering::SeqCst);
+                tasks.spawn(async move {
+                    let _ = stream
+                        .write_all(String::from(&unavailable()).as_bytes())
+                        .await;
+                });
+                continue;
+            }
+
+            let counted = Counted(connections.clone());
+            let router = self.router.clone();
+            let config = self.config.clone();
+            let shutdown = self.shutdown.clone();
+
+            tasks.spawn(async move {
+                let _counted = counted;
+                if let Err(e) = handle_connection(router, config, shutdown, stream, peer).await {
+                    eprintln!("Connection failed: {e}");
+                }
+            });
+        }
+
+        drop(listener);
+
+        // Aborting the connections still active at the deadline closes their sockets
+        let drain = async { while tasks.join_next().await.is_some() {} };
+        let _ = tokio::time::timeout(self.config.shutdown_timeout, drain).await;
+        tasks.shutdown().await;
+
+        Ok(())
+    }
+}
+
+async fn handle_connection(
+    router: Arc<Router>,
+    config: Arc<Config>,
+    shutdown: Arc<ShutdownState>,
+    stream: TcpStream,
+    peer: Peer,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut served = 0;
+
+    loop {
+        // Waits for the first byte of the next request within the keep-alive timeout, the
+        // connection being closed by the client, timing out or shutting down otherwise
+        let received = tokio::select! {
+            filled = within(config.idle_timeout, reader.fill_buf()) => {
+                matches!(filled, Some(Ok(buffer)) if !buffer.is_empty())
+            }
+            () = shutdown.stopped() => false,
+        };
+        if !received {
+            break;
+        }
+
+        let (response, keep_alive) =
+            match within(config.read_timeout, read_request(&mut reader, &config)).await {
+                Some(Ok(mut request)) => {
+                    served += 1;
+                    request.peer = Some(peer.clone());
+                    let (request, response) = dispatch(&router, request).await;
+
+                    persist(response, Some(&request), served, &config, &shutdown)
+                }
+                None | Some(Err(CONNECTION_CLOSED)) => break,
+                Some(Err(e)) => {
+                    let response = router.handle_error(&RequestError::Parse(e));
+                    persist(response, None, served, &config, &shutdown)
+                }
+            };
+
+        match within(config.write_timeout, write(&mut writer, &response)).await {
+            Some(result) => result?,
+            None => break,
+        }
+
+        if !keep_alive {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the head of the request up to its blank line, then its body.
+async fn read_request<R>(reader: &mut R, config: &Config) -> Result<Request, &'static str>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let max_header_bytes = config.limits.max_header_bytes;
+    let mut head = Vec::new();
+
+    loop {
+        // Reading with no bytes left would return nothing, again and again
+        let start = head.len();
+        if start == max_header_bytes {
+            return Err(HEADERS_TOO_LARGE);
+        }
+        (&mut *reader)
+            .take((max_header_bytes - start) as u64)
+            .read_until(b'\n', &mut head)
+            .await
+            .map_err(|_| CONNECTION_CLOSED)?;
+
+        if !head.ends_with(b"\n") {
+            return match head.len() == max_header_bytes {
+                true => Err(HEADERS_TOO_LARGE),
+                false => Err(CONNECTION_CLOSED),
+            };
+        }
+        if head[start..] == *b"\r\n" {
+            break;
+        }
+    }
+
+    let (request, content_length) = Request::read_head(&mut &head[..], &config.limits)?;
+
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .await
+        .map_err(|_| CONNECTION_CLOSED)?;
+
+    request.with_body(body)
+}
+
+/// Awaits the async handler of the route on the task of the connection, or dispatches the request
+/// on the blocking thread pool, where sync handlers and middleware may block.
+async fn dispatch(router: &Arc<Router>, mut request: Request) -> (Request, Response) {
+    if let Some(response) = router.dispatch_async(&mut request) {
+        let response = response.await;
+        return (request, response);
+    }
+
+    let router = router.clone();
+    let handle = Handle::current();
+
+    let (request, response) = tokio::task::spawn_blocking(move || {
+        SERVER_RUNTIME.with(|runtime| *runtime.borrow_mut() = Some(handle));
+        let response = router.dispatch(&mut request);
+        SERVER_RUNTIME.with(|runtime| *runtime.borrow_mut() = None);
+
+        (request, response)
+    })
+    .await
+    .expect("Dispatch catches panics");
+
+    (request, response)
+}
+
+async fn write<W: AsyncWrite + Unpin>(writer: &mut W, response: &Response) -> io::Result<()> {
+    writer.write_all(String::from(response).as_bytes()).await
+}
+
+/// Awaits the future within the timeout, zero disabling it.
+async fn within<F: Future>(timeout: Duration, future: F) -> Option<F::Output> {
+    match timeout.is_zero() {
+        true => Some(future.await),
+        false => tokio::time::timeout(timeout, future).await.ok(),
+    }
+}
+
+/// Catches a panic of the future, like [`panic::catch_unwind`].
+pub(crate) async fn catch_unwind<F: Future>(future: F) -> thread::Result<F::Output> {
+    let mut future = Box::pin(future);
+
+    std::future::poll_fn(|cx| {
+        match panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    })
+    .await
+}
+
+thread_local! {
+    /// The runtime of the [`AsyncServer`] whose blocking thread is dispatching a request.
+    static SERVER_RUNTIME: RefCell<Option<Handle>> = const { RefCell::new(None) };
+}
+
+/// Runs a future to completion from a synchronous handler, on the runtime of the
+/// [`AsyncServer`] dispatching the request or on a shared runtime otherwise, such as under
+/// [`Server`](super::Server).
+///
+/// The future is spawned while the thread waits for its output instead of blocking on it, which
+/// would panic on the threads of a runtime.
+pub(crate) fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    let handle = SERVER_RUNTIME
+        .with(|runtime| runtime.borrow().clone())
+        .unwrap_or_else(|| {
+            RUNTIME
+                .get_or_init(|| {
+                    tokio::runtime::Builder::new_multi_thread()
+                        .worker_threads(1)
+                        .enable_all()
+                        .build()
+                        .expect("Failed to start the async handler runtime")
+                })
+                .handle()
+                .clone()
+        });
+
+    let (sender, receiver) = mpsc::sync_channel(1);
+    handle.spawn(async move {
+        let _ = sender.send(catch_unwind(future).await);
+    });
+
+    match receiver.recv() {
+        Ok(Ok(output)) => output,
+        Ok(Err(payload)) => panic::resume_unwind(payload),
+        Err(_) => panic!("The async handler was cancelled"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        sync::{mpsc, Arc},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use tokio::sync::Barrier;
+
+    use crate::{
+        core::ServerBuilder,
+        http::{request::Request, Method},
+        router::{middleware::from_fn, Route, RouterBuilder},
+    };
+
+    #[test]
+    fn serve_async() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/sync", Method::Get, |_request| "sync"))
+            .with_route(Route::new_async("/async/:id", Method::Get, |request| {
+                let id = request.param("id").cloned().unwrap_or_default();
+
+                async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    format!("async {id}")
+                }
+            }))
+            .build();
+
+        let server = ServerBuilder::new(vec!["127.0.0.1:0".parse().unwrap()], router)
+            .try_build_async()
+            .unwrap();
+        let address = server.local_addr();
+        let handle = server.shutdown_handle();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let running = runtime.spawn(server.run());
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(
+                b"GET /sync HTTP/1.1\r\nHost: localhost\r\n\r\n\
+                  GET /async/42 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(response.contains("\r\n\r\nsync"));
+        assert!(response.ends_with("\r\n\r\nasync 42"));
+
+        handle.shutdown();
+        runtime.block_on(running).unwrap().unwrap();
+
+        // Async handlers also run outside of a runtime, or from the task of another one
+        let router = Arc::new(
+            RouterBuilder::new()
+                .with_route(Route::new_async("/", Method::Get, |_request| async {
+                    "ok"
+                }))
+                .build(),
+        );
+        let dispatch = move || {
+            let mut request =
+                Request::try_from("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            String::from(&router.dispatch(&mut request))
+        };
+        assert!(dispatch().ends_with("ok"));
+
+        let response = runtime.block_on(runtime.spawn(async move { dispatch() }));
+        assert!(response.unwrap().ends_with("ok"));
+    }
+
+    #[test]
+    fn async_header_limit() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Get, |_request| "ok"))
+            .build();
+
+        // Complete header lines filling the limit, still waiting for the blank line
+        let head = b"GET / HTTP/1.1\r\nHost: localhost\r\n";
+        let server = ServerBuilder::new(vec!["127.0.0.1:0".parse().unwrap()], router)
+            .with_max_header_bytes(head.len())
+            .try_build_async()
+            .unwrap();
+        let address = server.local_addr();
+        let handle = server.shutdown_handle();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let running = runtime.spawn(server.run());
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        stream.write_all(head).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+
+        handle.shutdown();
+        runtime.block_on(running).unwrap().unwrap();
+    }
+
+    #[test]
+    fn await_async_handlers() {
+        let barrier = Arc::new(Barrier::new(2));
+        let router = RouterBuilder::new()
+            .with_route(Route::new_async("/barrier", Method::Get, move |_request| {
+                let barrier = barrier.clone();
+                async move {
+                    let waited = tokio::time::timeout(Duration::from_secs(5), barrier.wait());
+                    match waited.await {
+                        Ok(_) => "met",
+                        Err(_) => "alone",
+                    }
+                }
+            }))
+            .with_route(Route::new_async::<_, _, ()>(
+                "/panic",
+                Method::Get,
+                |_request| async { panic!("async handler failed") },
+            ))
+            .with_route(
+                Route::new_async("/guarded", Method::Get, |_request| async { "guarded" })
+                    .with_guard(|_request: &Request| panic!("guard failed")),
+            )
+            .with_route(
+                Route::new_async("/wrapped", Method::Get, |_request| async { "wrapped" })
+                    .with_middleware(from_fn(|request, next| {
+                        next.run(request).with_header("X-Wrapped", "1")
+                    })),
+            )
+            .build();
+
+        let server = ServerBuilder::new(vec!["127.0.0.1:0".parse().unwrap()], router)
+            .try_build_async()
+            .unwrap();
+        let address = server.local_addr();
+        let handle = server.shutdown_handle();
+
+        // Async handlers waiting on each other would deadlock if each held a blocking thread
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .max_blocking_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let running = runtime.spawn(server.run());
+
+        let get = move |path: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(
+                    format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                        .as_bytes(),
+                )
+                .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let responses = [
+            thread::spawn(move || get("/barrier")),
+            thread::spawn(move || get("/barrier")),
+        ];
+        for response in responses {
+            assert!(response.join().unwrap().ends_with("\r\n\r\nmet"));
+        }
+
+        assert!(get("/panic").starts_with("HTTP/1.1 500 Internal Server Error"));
+        assert!(get("/guarded").starts_with("HTTP/1.1 500 Internal Server Error"));
+
+        let response = get("/wrapped");
+        assert!(response.contains("X-Wrapped: 1\r\n"));
+        assert!(response.ends_with("\r\n\r\nwrapped"));
+
+        handle.shutdown();
+        runtime.block_on(running).unwrap().unwrap();
+    }
+
+    #[test]
+    fn async_shutdown() {
+        let (started, start) = mpsc::channel();
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Get, |_request| "ok"))
+            .with_route(Route::new_async("/stuck", Method::Get, move |_request| {
+                started.send(()).unwrap();
+                tokio::time::sleep(Duration::from_secs(3))
+            }))
+            .build();
+
+        let server = ServerBuilder::new(vec!["127.0.0.1:0".parse().unwrap()], router)
+            .with_idle_timeout(Duration::ZERO)
+            .with_shutdown_timeout(Duration::from_millis(100))
+            .try_build_async()
+            .unwrap();
+        let address = server.local_addr();
+        let handle = server.shutdown_handle();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let running = runtime.spawn(server.run());
+
+        // Parked waiting for its next request once answered
+        let mut idle = TcpStream::connect(address).unwrap();
+        idle.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = Vec::new();
+        while !response.ends_with(b"ok") {
+            let mut buffer = [0; 1024];
+            let read = idle.read(&mut buffer).unwrap();
+            response.extend_from_slice(&buffer[..read]);
+        }
+
+        let mut stuck = TcpStream::connect(address).unwrap();
+        stuck
+            .write_all(b"GET /stuck HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        start.recv().unwrap();
+
+        let stopping = Instant::now();
+        handle.shutdown();
+        runtime.block_on(running).unwrap().unwrap();
+        assert!(stopping.elapsed() < Duration::from_secs(2));
+
+        // Both connections are closed, the stuck one without a response
+        for stream in [&mut idle, &mut stuck] {
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            assert!(response.is_empty());
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::{fmt, io};
+
+/// A failure preventing a [`Server`](super::Server) from starting or running.
+#[derive(Debug)]
+pub enum ServerError {
+    /// None of the addresses could be bound.
+    Bind(io::Error),
+    /// A worker thread could not be spawned.
+    Spawn(io::Error),
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::Bind(e) => write!(f, "Failed to bind the server: {e}"),
+            ServerError::Spawn(e) => write!(f, "Failed to spawn a worker thread: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServerError::Bind(e) | ServerError::Spawn(e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::{
+    env,
+    fs::{self, DirBuilder, Permissions},
+    ops::Range,
+    os::unix::{
+        fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+        io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::http::Peer;
+
+/// A listening socket, either TCP or Unix domain.
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, SocketFile),
+}
+
+/// The path of a Unix domain socket, removed once the listener is dropped if the server created
+/// it.
+#[cfg(unix)]
+pub(crate) struct SocketFile {
+    path: PathBuf,
+    owned: bool,
+}
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if self.owned {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Listener {
+    pub(crate) fn bind_tcp(addresses: &[SocketAddr]) -> io::Result<Self> {
+        TcpListener::bind(addresses).map(Listener::Tcp)
+    }
+
+    /// Binds a Unix domain socket at the path, replacing a stale socket left over by a process
+    /// which is no longer listening on it, and applying the permissions `mode` if any.
+    #[cfg(unix)]
+    pub(crate) fn bind_unix(path: &Path, mode: Option<u32>) -> io::Result<Self> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => match UnixStream::connect(path) {
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        "Another process is listening on the socket",
+                    ))
+                }
+                // Only a socket nobody listens on anymore refuses connections, other failures
+                // such as a full backlog or a denied permission say nothing of it
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(path)?,
+                Err(e) => return Err(e),
+            },
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "The socket path exists and is not a socket",
+                ))
+            }
+            Err(_) => {}
+        }
+
+        let listener = match mode {
+            Some(mode) => bind_private(path, mode)?,
+            None => UnixListener::bind(path)?,
+        };
+        let file = SocketFile {
+            path: path.to_path_buf(),
+            owned: true,
+        };
+
+        Ok(Listener::Unix(listener, file))
+    }
+
+    /// Serves a TCP listener opened by the caller.
+    pub(crate) fn from_tcp(listener: TcpListener) -> io::Result<Self> {
+        listener.set_nonblocking(false)?;
+        Ok(Listener::Tcp(listener))
+    }
+
+    /// Serves a Unix domain socket listener opened by the caller, which is left in place once the
+    /// server stops.
+    #[cfg(unix)]
+    pub(crate) fn from_unix(listener: UnixListener) -> io::Result<Self> {
+        let path = listener
+            .local_addr()?
+            .as_pathname()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::Unsupported, "The Unix socket has no path")
+            })?;
+
+        listener.set_nonblocking(false)?;
+        Ok(Listener::Unix(listener, SocketFile { path, owned: false }))
+    }
+
+    /// Serves a listening socket inherited as a file descriptor, either TCP or Unix domain.
+    #[cfg(unix)]
+    pub(crate) fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+        // Accepting on any other descriptor would fail right away, again and again
+        if socket_option(&fd, libc::SO_TYPE)? != libc::SOCK_STREAM {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The descriptor is not a stream socket",
+            ));
+        }
+        if socket_option(&fd, libc::SO_ACCEPTCONN)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The socket is not listening",
+            ));
+        }
+
+        // Only sockets of the IP families have an address TcpListener understands
+        let listener = TcpListener::from(fd);
+        if listener.local_addr().is_ok() {
+            return Self::from_tcp(listener);
+        }
+
+        Self::from_unix(UnixListener::from(OwnedFd::from(listener)))
+    }
+
+    /// Serves the first of the sockets passed by systemd socket activation, see [`systemd_fds`],
+    /// closing the others.
+    #[cfg(unix)]
+    pub(crate) fn from_systemd(mut fds: Range<RawFd>) -> io::Result<Self> {
+        // The descriptors are owned once, however many servers look them up
+        static TAKEN: AtomicBool = AtomicBool::new(false);
+        if !fds.is_empty() && TAKEN.swap(true, Ordering::SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "The sockets passed by systemd are already served",
+            ));
+        }
+
+        let first = fds.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "No socket passed by systemd")
+        })?;
+
+        for fd in fds {
+            // SAFETY: systemd hands the ownership of the sockets over to this process
+            drop(unsafe { OwnedFd::from_raw_fd(fd) });
+        }
+
+        // SAFETY: systemd hands the ownership of the sockets over to this process
+        let fd = unsafe { OwnedFd::from_raw_fd(first) };
+        // SAFETY: the descriptor is valid and owned, setting a flag has no other effect
+        unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+        Self::from_fd(fd)
+    }
+
+    pub(crate) fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().map(|(stream, _)| Stream::Tcp(stream)),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => {
+                listener.accept().map(|(stream, _)| Stream::Unix(stream))
+            }
+        }
+    }
+
+    pub(crate) fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Listener::Unix(..) => None,
+        }
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn local_path(&self) -> Option<&Path> {
+        match self {
+            Listener::Tcp(_) => None,
+            Listener::Unix(_, file) => Some(&file.path),
+        }
+    }
+
+    /// Returns where to connect to wake the listener up from a blocking accept.
+    pub(crate) fn endpoint(&self) -> io::Result<Endpoint> {
+        match self {
+            Listener::Tcp(listener) => {
+                let address = listener.local_addr()?;
+                let ip = match address.ip() {
+                    IpAddr::V4(ip) if ip.is_unspecified() => Ipv4Addr::LOCALHOST.into(),
+                    IpAddr::V6(ip) if ip.is_unspecified() => Ipv6Addr::LOCALHOST.into(),
+                    ip => ip,
+                };
+
+                Ok(Endpoint::Tcp(SocketAddr::new(ip, address.port())))
+            }
+            #[cfg(unix)]
+            Listener::Unix(_, file) => Ok(Endpoint::Unix(file.path.clone())),
+        }
+    }
+}
+
+/// The first descriptor of socket activation, following the standard ones.
+#[cfg(unix)]
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The variables describing the sockets passed by systemd socket activation.
+#[cfg(unix)]
+pub(crate) const LISTEN_VARS: [&str; 3] = ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"];
+
+/// Returns the descriptors passed by systemd socket activation to this process, leaving the
+/// environment untouched.
+#[cfg(unix)]
+pub(crate) fn systemd_fds() -> io::Result<Range<RawFd>> {
+    listen_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+    )
+}
+
+/// Returns the descriptors passed by systemd socket activation, given the values of the
+/// `LISTEN_PID` and `LISTEN_FDS` variables.
+#[cfg(unix)]
+fn listen_fds(pid: Option<&str>, fds: Option<&str>) -> io::Result<Range<RawFd>> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidInput, message);
+
+    // The sockets were passed to a parent process, and the variables inherited from it
+    let pid =
+        pid.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "LISTEN_PID is not set"))?;
+    if pid
+        .parse::<u32>()
+        .map_err(|_| invalid("Invalid LISTEN_PID"))?
+        != std::process::id()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "The sockets were passed to another process",
+        ));
+    }
+
+    let fds = fds
+        .ok_or_else(|| invalid("LISTEN_FDS is not set"))?
+        .parse::<RawFd>()
+        .map_err(|_| invalid("Invalid LISTEN_FDS"))?;
+    if fds < 0 {
+        return Err(invalid("Invalid LISTEN_FDS"));
+    }
+
+    Ok(LISTEN_FDS_START..LISTEN_FDS_START + fds)
+}
+
+/// The address of a listener, see [`Listener::endpoint`].
+pub(crate) enum Endpoint {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    pub(crate) fn connect(&self) {
+        match self {
+            Endpoint::Tcp(address) => {
+                let _ = TcpStream::connect_timeout(address, Duration::from_secs(1));
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let _ = UnixStream::connect(path);
+            }
+        }
+    }
+}
+
+/// An accepted connection, either TCP or Unix domain.
+pub(crate) enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+        }
+    }
+
+    pub(crate) fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(how),
+        }
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    pub(crate) fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+
+    pub(crate) fn peer(&self) -> io::Result<Peer> {
+        match self {
+            Stream::Tcp(stream) => stream.peer_addr().map(Peer::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Ok(Peer::Unix(credentials(stream))),
+        }
+    }
+}
+
+impl Read for &Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => (&*stream).read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => (&*stream).read(buf),
+        }
+    }
+}
+
+impl Write for &Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => (&*stream).write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => (&*stream).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => (&*stream).flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => (&*stream).flush(),
+        }
+    }
+}
+
+/// Reads from or writes to a stream until a deadline, which bounds a whole request or response
+/// rather than each read or write: the time left is set as the timeout of every call, and calls
+/// past the deadline fail as timed out.
+pub(crate) struct Timed<'a> {
+    stream: &'a Stream,
+    deadline: Option<Instant>,
+}
+
+impl<'a> Timed<'a> {
+    /// Starts a deadline after the timeout, zero disabling it.
+    pub(crate) fn new(stream: &'a Stream, timeout: Duration) -> Self {
+        let mut timed = Self {
+            stream,
+            deadline: None,
+        };
+        timed.restart(timeout);
+        timed
+    }
+
+    /// Replaces the deadline with one after the timeout, zero disabling it.
+    pub(crate) fn restart(&mut self, timeout: Duration) {
+        self.deadline = (!timeout.is_zero()).then(|| Instant::now() + timeout);
+    }
+
+    /// Returns the time left before the deadline, failing once it has passed.
+    fn remaining(&self) -> io::Result<Option<Duration>> {
+        let Some(deadline) = self.deadline else {
+            return Ok(None);
+        };
+
+        match deadline.saturating_duration_since(Instant::now()) {
+            Duration::ZERO => Err(io::ErrorKind::TimedOut.into()),
+            remaining => Ok(Some(remaining)),
+        }
+    }
+}
+
+impl Read for Timed<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(self.remaining()?)?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Timed<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(self.remaining()?)?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Binds a Unix domain socket in a private directory next to the path and links it at the path
+/// once its permissions are applied, so that no client can connect with the permissions derived
+/// from the umask in between.
+#[cfg(unix)]
+fn bind_private(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let dir = parent.join(format!(
+        ".verglas-{}-{}",
+        process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    DirBuilder::new().mode(0o700).create(&dir)?;
+
+    let private = dir.join("socket");
+    let listener = UnixListener::bind(&private).and_then(|listener| {
+        fs::set_permissions(&private, Permissions::from_mode(mode))?;
+        // Unlike a rename, fails instead of replacing a socket bound at the path meanwhile
+        fs::hard_link(&private, path)?;
+        Ok(listener)
+    });
+
+    let _ = fs::remove_file(&private);
+    let _ = fs::remove_dir(&dir);
+    listener
+}
+
+/// Returns the value of an integer option of the socket.
+#[cfg(unix)]
+fn socket_option(fd: &OwnedFd, option: libc::c_int) -> io::Result<libc::c_int> {
+    let mut value: libc::c_int = 0;
+    let mut length = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+
+    // SAFETY: the buffer and its length describe a valid integer owned by this frame
+    let result = unsafe {
+        libc::getsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            option,
+            (&mut value as *mut libc::c_int).cast(),
+            &mut length,
+        )
+    };
+
+    match result {
+        0 => Ok(value),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Returns the credentials of the process at the other end of the socket.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn credentials(stream: &UnixStream) -> Option<crate::http::Credentials> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    // SAFETY: the buffer and its length describe a valid `ucred` owned by this frame
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut length,
+        )
+    };
+
+    (result == 0).then_some(crate::http::Credentials {
+        uid: credentials.uid,
+        gid: credentials.gid,
+        pid: Some(credentials.pid),
+    })
+}
+
+/// Returns the credentials of the process at the other end of the socket.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn credentials(stream: &UnixStream) -> Option<crate::http::Credentials> {
+    let mut uid = 0;
+    let mut gid = 0;
+
+    // SAFETY: both pointers refer to integers owned by this frame
+    let result = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+
+    (result == 0).then_some(crate::http::Credentials {
+        uid,
+        gid,
+        pid: None,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::io::ErrorKind;
+
+    use super::listen_fds;
+
+    #[test]
+    fn parse_listen_fds() {
+        let pid = std::process::id().to_string();
+
+        assert_eq!(listen_fds(Some(&pid), Some("2")).unwrap(), 3..5);
+        assert_eq!(listen_fds(Some(&pid), Some("0")).unwrap(), 3..3);
+        assert_eq!(
+            listen_fds(Some("1"), Some("2")).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            listen_fds(None, Some("2")).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            listen_fds(Some(&pid), Some("two")).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed number of worker threads running jobs from a bounded queue.
+pub(crate) struct ThreadPool {
+    workers: Vec<JoinHandle<()>>,
+    sender: Option<SyncSender<Job>>,
+    /// The number of workers which exited, notified as each one does.
+    exited: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl ThreadPool {
+    /// Spawns `size` workers sharing a queue of at most `queue_size` pending jobs.
+    pub(crate) fn new(size: usize, queue_size: usize) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        // Dropping the pool on failure stops the workers already spawned
+        let mut pool = Self {
+            workers: Vec::with_capacity(size),
+            sender: Some(sender),
+            exited: Arc::new((Mutex::new(0), Condvar::new())),
+        };
+
+        for id in 0..size.max(1) {
+            let receiver = receiver.clone();
+            let exited = pool.exited.clone();
+
+            pool.workers.push(
+                thread::Builder::new()
+                    .name(format!("verglas-worker-{id}"))
+                    .spawn(move || {
+                        Self::work(&receiver);
+
+                        let (count, condvar) = &*exited;
+                        *count.lock().unwrap() += 1;
+                        condvar.notify_all();
+                    })?,
+            );
+        }
+
+        Ok(pool)
+    }
+
+    /// Queues the job, handing it back if the queue is full.
+    pub(crate) fn try_execute<F>(&self, job: F) -> Result<(), Job>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let sender = self.sender.as_ref().expect("Pool is running");
+
+        match sender.try_send(Box::new(job)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(job) | TrySendError::Disconnected(job)) => Err(job),
+        }
+    }
+
+    /// Lets the workers finish the queued jobs, waiting for them until the deadline. Workers still
+    /// running a job afterwards are detached instead of joined.
+    pub(crate) fn shutdown(mut self, deadline: Instant) {
+        drop(self.sender.take());
+
+        let (count, condvar) = &*self.exited;
+        let mut exited = count.lock().unwrap();
+        while *exited < self.workers.len() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            exited = condvar.wait_timeout(exited, deadline - now).unwrap().0;
+        }
+
+        if *exited < self.workers.len() {
+            self.workers.clear();
+        }
+    }
+
+    fn work(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            // The lock is released before running the job so other workers can pick the next one
+            let job = match receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => return,
+            };
+
+            match job {
+                // A panicking job must not take the worker down with it
+                Ok(job) => {
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                }
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    /// Lets the workers finish the queued jobs, then waits for them to exit.
+    fn drop(&mut self) {
+        drop(self.sender.take());
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    };
+
+    use super::ThreadPool;
+
+    #[test]
+    fn bounded_queue() {
+        let pool = ThreadPool::new(1, 1).unwrap();
+        let (unblock, blocked) = mpsc::channel::<()>();
+        let (started, start) = mpsc::channel::<()>();
+        let done = Arc::new(AtomicUsize::new(0));
+
+        assert!(pool
+            .try_execute(move || {
+                started.send(()).unwrap();
+                blocked.recv().unwrap();
+            })
+            .is_ok());
+        start.recv().unwrap();
+
+        let counter = done.clone();
+        assert!(pool
+            .try_execute(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .is_ok());
+        assert!(pool.try_execute(|| panic!("Not queued")).is_err());
+
+        unblock.send(()).unwrap();
+        drop(pool);
+        assert_eq!(done.load(Ordering::SeqCst), 1);
+    }
+}
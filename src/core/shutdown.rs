@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    net::Shutdown,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::Instant,
+};
+#[cfg(unix)]
+use std::{io, mem, ptr, thread};
+
+use super::listener::{Endpoint, Stream};
+
+/// Stops a running [`Server`](super::Server) from another thread.
+///
+/// The shutdown locks and allocates, so it must not be started from a signal handler: use
+/// [`ShutdownHandle::shutdown_on_signals`] to stop the server on SIGTERM instead.
+///
+/// Cloning the handle is cheap, every clone stopping the same server.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    state: Arc<ShutdownState>,
+}
+
+impl ShutdownHandle {
+    /// Asks the server to stop accepting connections, then to return from
+    /// [`Server::run`](super::Server::run) once the active connections are done.
+    pub fn shutdown(&self) {
+        if self.state.stopping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        #[cfg(feature = "tokio")]
+        self.state.stopped.send_replace(true);
+
+        // Connections waiting for their next request have nothing left to finish, closing their
+        // read side lets a request already received complete
+        for connection in self.state.connections.lock().unwrap().values_mut() {
+            if !connection.busy {
+                let _ = connection.stream.shutdown(Shutdown::Read);
+                connection.closed = true;
+            }
+        }
+
+        // Wakes the listener up from its blocking accept
+        self.state.endpoint.connect();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.state.is_stopping()
+    }
+
+    /// Shuts the server down once the process receives SIGTERM or SIGINT.
+    ///
+    /// Both signals are blocked in the calling thread and waited for with `sigwait` on a dedicated
+    /// thread, which then calls [`ShutdownHandle::shutdown`]. Threads inherit the signals blocked
+    /// by the thread spawning them, and a thread which does not block them still terminates the
+    /// process: call it from the main thread before starting the server, a tokio runtime or any
+    /// other thread.
+    #[cfg(unix)]
+    pub fn shutdown_on_signals(&self) -> io::Result<()> {
+        // SAFETY: an all-zero set is a valid value, emptied before use anyway
+        let mut signals: libc::sigset_t = unsafe { mem::zeroed() };
+        // SAFETY: the set is valid and the signals exist
+        unsafe {
+            libc::sigemptyset(&mut signals);
+            libc::sigaddset(&mut signals, libc::SIGTERM);
+            libc::sigaddset(&mut signals, libc::SIGINT);
+        }
+
+        // SAFETY: the set is valid and the previous mask is not requested
+        let result = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &signals, ptr::null_mut()) };
+        if result != 0 {
+            return Err(io::Error::from_raw_os_error(result));
+        }
+
+        let handle = self.clone();
+        thread::Builder::new()
+            .name("verglas-signals".to_string())
+            .spawn(move || {
+                let mut signal = 0;
+                // SAFETY: the set and the signal number are valid for the duration of the call
+                if unsafe { libc::sigwait(&signals, &mut signal) } == 0 {
+                    handle.shutdown();
+                }
+            })?;
+
+        Ok(())
+    }
+}
+
+struct Tracked {
+    stream: Stream,
+    busy: bool,
+    /// Whether the read side was closed by the shutdown.
+    closed: bool,
+}
+
+/// The state shared by the server, its connections and its shutdown handles.
+pub(crate) struct ShutdownState {
+    stopping: AtomicBool,
+    endpoint: Endpoint,
+    next_id: AtomicUsize,
+    connections: Mutex<HashMap<usize, Tracked>>,
+    closed: Condvar,
+    /// Wakes the tasks of an [`AsyncServer`](super::AsyncServer) up on shutdown.
+    #[cfg(feature = "tokio")]
+    stopped: tokio::sync::watch::Sender<bool>,
+}
+
+impl ShutdownState {
+    pub(crate) fn new(endpoint: Endpoint) -> Arc<Self> {
+        Arc::new(Self {
+            stopping: AtomicBool::new(false),
+            endpoint,
+            next_id: AtomicUsize::new(0),
+            connections: Mutex::new(HashMap::new()),
+            closed: Condvar::new(),
+            #[cfg(feature = "tokio")]
+            stopped: tokio::sync::watch::Sender::new(false),
+        })
+    }
+
+    pub(crate) fn handle(self: &Arc<Self>) -> ShutdownHandle {
+        ShutdownHandle {
+            state: self.clone(),
+        }
+    }
+
+    pub(crate) fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Completes once the server is stopping.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn stopped(&self) {
+        let _ = self.stopped.subscribe().wait_for(|stopped| *stopped).await;
+    }
+
+    /// Tracks the connection until the returned guard is dropped, or returns `None` if the server
+    /// is stopping.
+    pub(crate) fn track(self: &Arc<Self>, stream: &Stream) -> Option<ConnectionGuard> {
+        let stream = stream.try_clone().ok()?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let mut connections = self.connections.lock().unwrap();
+        if self.is_stopping() {
+            return None;
+        }
+        connections.insert(
+            id,
+            Tracked {
+                stream,
+                busy: false,
+                closed: false,
+            },
+        );
+
+        Some(ConnectionGuard {
+            state: self.clone(),
+            id,
+        })
+    }
+
+    /// Waits for the tracked connections to close until the deadline, then closes the remaining
+    /// ones.
+    pub(crate) fn drain(&self, deadline: Instant) {
+        let mut connections = self.connections.lock().unwrap();
+
+        while !connections.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            connections = self
+                .closed
+                .wait_timeout(connections, deadline - now)
+                .unwrap()
+                .0;
+        }
+
+        for connection in connections.values() {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Returns the number of tracked connections and how many of them are busy.
+    #[cfg(test)]
+    pub(crate) fn count(&self) -> (usize, usize) {
+        let connections = self.connections.lock().unwrap();
+        let busy = connections.values().filter(|connection| connection.busy);
+
+        (connections.len(), busy.count())
+    }
+}
+
+/// Keeps a connection tracked while it is served.
+pub(crate) struct ConnectionGuard {
+    state: Arc<ShutdownState>,
+    id: usize,
+}
+
+impl ConnectionGuard {
+    /// Marks the connection as serving a request from its first byte, so it is left to finish it
+    /// on shutdown. Returns `false` if the shutdown already closed the connection.
+    pub(crate) fn busy(&self) -> bool {
+        match self.state.connections.lock().unwrap().get_mut(&self.id) {
+            Some(connection) if !connection.closed => {
+                connection.busy = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Marks the connection as waiting for its next request, returning `false` if the server is
+    /// stopping and the connection should be closed.
+    pub(crate) fn idle(&self) -> bool {
+        if let Some(connection) = self.state.connections.lock().unwrap().get_mut(&self.id) {
+            connection.busy = false;
+        }
+        !self.state.is_stopping()
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut connections) = self.state.connections.lock() {
+            connections.remove(&self.id);
+        }
+        self.state.closed.notify_all();
+    }
+}
@@ -0,0 +1,480 @@
+//! Typed extraction of request data for handlers registered with
+//! [`Route::typed`](crate::router::Route::typed).
+//!
+//! Each argument of a typed handler implements [`FromRequest`] and is extracted before the handler
+//! runs. When an extraction fails the handler is skipped and the request is answered with a 400
+//! describing the failure.
+
+use std::{fmt, marker::PhantomData, str::FromStr};
+
+use crate::http::{
+    request::Request,
+    response::{IntoResponse, Response, ResponseBuilder},
+};
+
+/// A failed extraction, answered with a 400 carrying its message.
+///
+/// Handlers extracting values themselves can return it with `?` when returning a `Result`.
+#[derive(Debug, PartialEq)]
+pub struct Rejection {
+    pub message: String,
+}
+
+impl Rejection {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        ResponseBuilder::new()
+            .bad_request()
+            .with_body(&self.message)
+            .build()
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A value which can be extracted from a request.
+pub trait FromRequest: Sized {
+    fn from_request(request: &Request) -> Result<Self, Rejection>;
+
+    /// Extracts the value for an [`Option`] argument, returning `None` when the request does not
+    /// carry it. Values which are present but invalid are still rejected.
+    ///
+    /// Defaults to [`FromRequest::from_request`], for values which are never absent.
+    fn from_request_optional(request: &Request) -> Result<Option<Self>, Rejection> {
+        Self::from_request(request).map(Some)
+    }
+}
+
+impl<T: FromRequest> FromRequest for Option<T> {
+    fn from_request(request: &Request) -> Result<Self, Rejection> {
+        T::from_request_optional(request)
+    }
+}
+
+/// A handler taking extractors as arguments, implemented for functions and closures of up to
+/// eight arguments implementing [`FromRequest`] and returning a value implementing
+/// [`IntoResponse`].
+pub trait Handler<Args>: Send + Sync + 'static {
+    fn call(&self, request: &Request) -> Response;
+}
+
+macro_rules! impl_handler {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> Handler<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync + 'static,
+            R: IntoResponse,
+            $($arg: FromRequest,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&self, request: &Request) -> Response {
+                $(
+                    let $arg = match $arg::from_request(request) {
+                        Ok(value) => value,
+                        Err(rejection) => return rejection.into_response(),
+                    };
+                )*
+
+                self($($arg),*).into_response()
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(T1);
+impl_handler!(T1, T2);
+impl_handler!(T1, T2, T3);
+impl_handler!(T1, T2, T3, T4);
+impl_handler!(T1, T2, T3, T4, T5);
+impl_handler!(T1, T2, T3, T4, T5, T6);
+impl_handler!(T1, T2, T3, T4, T5, T6, T7);
+impl_handler!(T1, T2, T3, T4, T5, T6, T7, T8);
+
+fn parse<T>(kind: &str, name: &str, value: &str) -> Result<T, Rejection>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| Rejection::new(&format!("Invalid {kind} {name}: {e}")))
+}
+
+/// Values which can be parsed from the path parameters of a route, in pattern order.
+///
+/// Implemented for single values, for routes with exactly one parameter, and for tuples of values.
+pub trait FromParams: Sized {
+    fn from_params(params: &[(String, String)]) -> Result<Self, Rejection>;
+}
+
+macro_rules! impl_from_params_value {
+    ($($ty:ty),*) => {
+        $(
+            impl FromParams for $ty {
+                fn from_params(params: &[(String, String)]) -> Result<Self, Rejection> {
+                    match params {
+                        [(name, value)] => parse("path parameter", name, value),
+                        _ => Err(Rejection::new(&format!(
+                            "Expected 1 path parameter, found {}",
+                            params.len()
+                        ))),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_params_value!(
+    String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+macro_rules! impl_from_params_tuple {
+    ($len:literal, $($ty:ident),*) => {
+        impl<$($ty,)*> FromParams for ($($ty,)*)
+        where
+            $($ty: FromStr, $ty::Err: fmt::Display,)*
+        {
+            #[allow(non_snake_case)]
+            fn from_params(params: &[(String, String)]) -> Result<Self, Rejection> {
+                match params {
+                    [$($ty,)*] => Ok(($(parse("path parameter", &$ty.0, &$ty.1)?,)*)),
+                    _ => Err(Rejection::new(&format!(
+                        "Expected {} path parameters, found {}",
+                        $len,
+                        params.len()
+                    ))),
+                }
+            }
+        }
+    };
+}
+
+impl_from_params_tuple!(1, T1);
+impl_from_params_tuple!(2, T1, T2);
+impl_from_params_tuple!(3, T1, T2, T3);
+impl_from_params_tuple!(4, T1, T2, T3, T4);
+
+/// Extracts the path parameters captured by the route, e.g. `Path(id): Path<u32>` for
+/// `/users/:id` or `Path((user, post)): Path<(u32, u32)>` for `/users/:user/posts/:post`.
+#[derive(Debug, PartialEq)]
+pub struct Path<T>(pub T);
+
+impl<T: FromParams> FromRequest for Path<T> {
+    fn from_request(request: &Request) -> Result<Self, Rejection> {
+        T::from_params(&request.params).map(Path)
+    }
+}
+
+/// Names the header or cookie read by a [`Header`] or [`Cookie`] extractor.
+///
+/// ```
+/// use verglas::extract::{Header, Named};
+///
+/// struct UserAgent;
+///
+/// impl Named for UserAgent {
+///     const NAME: &'static str = "User-Agent";
+/// }
+///
+/// fn handler(Header(user_agent, _): Header<UserAgent>) {}
+/// ```
+pub trait Named {
+    const NAME: &'static str;
+}
+
+/// Extracts the value of the header named by `N`, parsed as a `T`. Rejects requests without the
+/// header unless wrapped in an [`Option`].
+pub struct Header<N, T = String>(pub T, pub PhantomData<fn() -> N>);
+
+impl<N, T> FromRequest for Header<N, T>
+where
+    N: Named,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn from_request(request: &Request) -> Result<Self, Rejection> {
+        Self::from_request_optional(request)?
+            .ok_or_else(|| Rejection::new(&format!("Missing header {}", N::NAME)))
+    }
+
+    fn from_request_optional(request: &Request) -> Result<Option<Self>, Rejection> {
+        request
+            .get_header(N::NAME)
+            .map(|value| Ok(Header(parse("header", N::NAME, value)?, PhantomData)))
+            .transpose()
+    }
+}
+
+/// Extracts the value of the cookie named by `N`, parsed as a `T`. Rejects requests without the
+/// cookie unless wrapped in an [`Option`].
+pub struct Cookie<N, T = String>(pub T, pub PhantomData<fn() -> N>);
+
+impl<N, T> FromRequest for Cookie<N, T>
+where
+    N: Named,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn from_request(request: &Request) -> Result<Self, Rejection> {
+        Self::from_request_optional(request)?
+            .ok_or_else(|| Rejection::new(&format!("Missing cookie {}", N::NAME)))
+    }
+
+    fn from_request_optional(request: &Request) -> Result<Option<Self>, Rejection> {
+        request
+            .get_cookie(N::NAME)
+            .map(|cookie| {
+                Ok(Cookie(
+                    parse("cookie", N::NAME, &cookie.value)?,
+                    PhantomData,
+                ))
+            })
+            .transpose()
+    }
+}
+
+/// Extracts the raw body of the request, empty when the request has none.
+pub struct Body(pub String);
+
+impl FromRequest for Body {
+    fn from_request(request: &Request) -> Result<Self, Rejection> {
+        Ok(Body(request.body.clone().unwrap_or_default()))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_extract {
+    use serde::de::DeserializeOwned;
+
+    use super::{FromRequest, Rejection};
+    use crate::http::request::Request;
+
+    fn content_type(request: &Request, expected: &str) -> Result<(), Rejection> {
+        let content_type = request
+            .get_header("Content-Type")
+            .map(|value| value.split(';').next().unwrap_or_default().trim());
+
+        match content_type {
+            Some(content_type) if content_type.eq_ignore_ascii_case(expected) => Ok(()),
+            _ => Err(Rejection::new(&format!(
+                "Expected a request with Content-Type {expected}"
+            ))),
+        }
+    }
+
+    fn has_body(request: &Request) -> bool {
+        request.body.as_deref().is_some_and(|body| !body.is_empty())
+    }
+
+    /// Deserializes the query string of the request into a `T`, absent when the request has no
+    /// query string. Requires the `serde` feature.
+    #[derive(Debug, PartialEq)]
+    pub struct Query<T>(pub T);
+
+    impl<T: DeserializeOwned> FromRequest for Query<T> {
+        fn from_request(request: &Request) -> Result<Self, Rejection> {
+            let query = request
+                .uri
+                .attributes
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join("&");
+
+            serde_urlencoded::from_str(&query)
+                .map(Query)
+                .map_err(|e| Rejection::new(&format!("Invalid query string: {e}")))
+        }
+
+        fn from_request_optional(request: &Request) -> Result<Option<Self>, Rejection> {
+            match request.uri.attributes.is_empty() {
+                true => Ok(None),
+                false => Self::from_request(request).map(Some),
+            }
+        }
+    }
+
+    /// Deserializes an `application/x-www-form-urlencoded` body into a `T`, absent when the request
+    /// has no body. Requires the `serde` feature.
+    #[derive(Debug, PartialEq)]
+    pub struct Form<T>(pub T);
+
+    impl<T: DeserializeOwned> FromRequest for Form<T> {
+        fn from_request(request: &Request) -> Result<Self, Rejection> {
+            content_type(request, "application/x-www-form-urlencoded")?;
+
+            serde_urlencoded::from_str(request.body.as_deref().unwrap_or_default())
+                .map(Form)
+                .map_err(|e| Rejection::new(&format!("Invalid form body: {e}")))
+        }
+
+        fn from_request_optional(request: &Request) -> Result<Option<Self>, Rejection> {
+            match has_body(request) {
+                true => Self::from_request(request).map(Some),
+                false => Ok(None),
+            }
+        }
+    }
+
+    /// Deserializes an `application/json` body into a `T`, absent when the request has no body.
+    /// Requires the `serde` feature.
+    #[derive(Debug, PartialEq)]
+    pub struct Json<T>(pub T);
+
+    impl<T: DeserializeOwned> FromRequest for Json<T> {
+        fn from_request(request: &Request) -> Result<Self, Rejection> {
+            content_type(request, "application/json")?;
+
+            serde_json::from_str(request.body.as_deref().unwrap_or_default())
+                .map(Json)
+                .map_err(|e| Rejection::new(&format!("Invalid JSON body: {e}")))
+        }
+
+        fn from_request_optional(request: &Request) -> Result<Option<Self>, Rejection> {
+            match has_body(request) {
+                true => Self::from_request(request).map(Some),
+                false => Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use serde_extract::{Form, Json, Query};
+
+#[cfg(test)]
+mod test {
+    use super::{Cookie, FromRequest, Header, Named, Path, Rejection};
+    use crate::http::request::Request;
+
+    struct Session;
+
+    impl Named for Session {
+        const NAME: &'static str = "session";
+    }
+
+    struct Limit;
+
+    impl Named for Limit {
+        const NAME: &'static str = "X-Limit";
+    }
+
+    fn request(request: &str, params: &[(&str, &str)]) -> Request {
+        let mut request = Request::try_from(request).unwrap();
+        request.params = params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        request
+    }
+
+    #[test]
+    fn extract_path() {
+        let request = request("GET / HTTP/1.0", &[("user", "42"), ("post", "first")]);
+
+        let Path((user, post)) = Path::<(u32, String)>::from_request(&request).unwrap();
+        assert_eq!(user, 42);
+        assert_eq!(post, "first");
+
+        assert_eq!(
+            Path::<u32>::from_request(&request),
+            Err(Rejection::new("Expected 1 path parameter, found 2"))
+        );
+        assert_eq!(
+            Path::<(u32, u32)>::from_request(&request),
+            Err(Rejection::new(
+                "Invalid path parameter post: invalid digit found in string"
+            ))
+        );
+    }
+
+    #[test]
+    fn extract_header_and_cookie() {
+        let request = request(
+            "GET / HTTP/1.0\r\nX-Limit: 10\r\nCookie: session=abc\r\n\r\n",
+            &[],
+        );
+
+        let Header(limit, _) = Header::<Limit, u8>::from_request(&request).unwrap();
+        assert_eq!(limit, 10);
+
+        let Cookie(session, _) = Cookie::<Session>::from_request(&request).unwrap();
+        assert_eq!(session, "abc");
+
+        let request = super::test::request("GET / HTTP/1.0", &[]);
+        assert_eq!(
+            Header::<Limit>::from_request(&request).err(),
+            Some(Rejection::new("Missing header X-Limit"))
+        );
+        assert!(Option::<Cookie<Session>>::from_request(&request)
+            .unwrap()
+            .is_none());
+
+        let request = super::test::request("GET / HTTP/1.0\r\nX-Limit: abc\r\n\r\n", &[]);
+        assert_eq!(
+            Option::<Header<Limit, u8>>::from_request(&request).err(),
+            Some(Rejection::new(
+                "Invalid header X-Limit: invalid digit found in string"
+            ))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn extract_serde() {
+        use super::{Form, Json, Query};
+
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Search {
+            term: String,
+            page: u32,
+        }
+
+        let request = super::test::request("GET /?term=hello%20world&page=2 HTTP/1.0", &[]);
+        let Query(search) = Query::<Search>::from_request(&request).unwrap();
+        assert_eq!(
+            search,
+            Search {
+                term: "hello world".to_string(),
+                page: 2
+            }
+        );
+
+        let body = "term=rust&page=1";
+        let request = super::test::request(
+            &format!(
+                "POST / HTTP/1.0\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            ),
+            &[],
+        );
+        assert!(Form::<Search>::from_request(&request).is_ok());
+        assert!(Json::<Search>::from_request(&request).is_err());
+
+        let body = r#"{"term":"rust","page":"one"}"#;
+        let request = super::test::request(
+            &format!(
+                "POST / HTTP/1.0\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            ),
+            &[],
+        );
+        let rejection = Json::<Search>::from_request(&request).err().unwrap();
+        assert!(rejection
+            .message
+            .starts_with("Invalid JSON body: invalid type"));
+    }
+}
@@ -3,8 +3,10 @@ pub mod request;
 pub mod response;
 pub mod types;
 
+use std::net::SocketAddr;
+
 /// The Mehtod enum represents an HTTP method as defined in [RFC 2616](https://datatracker.ietf.org/doc/html/rfc2616#section-5.1.1)
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Method {
     Get,
     Head,
@@ -16,7 +18,7 @@ pub enum Method {
     Trace,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Version {
     Http1_0,
     Http1_1,
@@ -27,3 +29,21 @@ pub struct Uri {
     pub path: String,
     pub attributes: Vec<(String, String)>,
 }
+
+/// The client at the other end of a connection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Peer {
+    Tcp(SocketAddr),
+    /// A process connected through a Unix domain socket, with its credentials when the platform
+    /// provides them.
+    Unix(Option<Credentials>),
+}
+
+/// The identity of the process at the other end of a Unix domain socket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+    /// The process id, only known on Linux and Android.
+    pub pid: Option<i32>,
+}
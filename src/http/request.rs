@@ -1,10 +1,40 @@
-use super::{cookie::RequestCookie, Method, Uri, Version};
+use super::{cookie::RequestCookie, Method, Peer, Uri, Version};
+use crate::state::State;
 use std::{
     collections::HashMap,
-    io::{BufRead, BufReader, Read},
+    io::{BufRead, BufReader, ErrorKind, Read},
     net::TcpStream,
+    sync::Arc,
 };
 
+/// The parse error returned when the client closed the connection instead of sending a request.
+pub(crate) const CONNECTION_CLOSED: &str = "Connection closed";
+/// The parse error returned when the request line and headers exceed [`Limits::max_header_bytes`].
+pub const HEADERS_TOO_LARGE: &str = "Request headers too large";
+/// The parse error returned when the body exceeds [`Limits::max_body_bytes`].
+pub const BODY_TOO_LARGE: &str = "Request body too large";
+/// The parse error returned when the request has a `Transfer-Encoding`, whose bodies are not
+/// supported.
+pub const UNSUPPORTED_TRANSFER_ENCODING: &str = "Transfer-Encoding not supported";
+
+/// Size limits enforced while reading a request from a stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// The maximum size of the request line and headers, including line breaks.
+    pub max_header_bytes: usize,
+    /// The maximum size of the body.
+    pub max_body_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_header_bytes: 8 * 1024,
+            max_body_bytes: 1024 * 1024,
+        }
+    }
+}
+
 // https://datatracker.ietf.org/doc/html/rfc2616#section-5\
 #[derive(Debug)]
 pub struct Request {
@@ -14,35 +44,122 @@ pub struct Request {
     pub headers: HashMap<String, String>,
     pub body: Option<String>,
     pub cookies: Vec<RequestCookie>,
+    pub params: Vec<(String, String)>,
+    /// The client which sent the request, set by the server.
+    pub peer: Option<Peer>,
+    /// The states of the routers serving the request, innermost first.
+    pub(crate) state: Vec<Arc<State>>,
 }
 
 impl Request {
     pub fn contains_header(&self, header: &str) -> bool {
-        self.headers.contains_key(header)
+        self.get_header(header).is_some()
     }
 
+    /// Returns the value of the header, matching its name case-insensitively.
     pub fn get_header(&self, header: &str) -> Option<&String> {
-        self.headers.get(header)
+        get_header(&self.headers, header)
+    }
+
+    /// Returns whether the client allows the connection to stay open after the response, which is
+    /// the default for HTTP/1.1 unless `Connection: close` is sent, while HTTP/1.0 requires
+    /// `Connection: keep-alive`.
+    pub fn keep_alive(&self) -> bool {
+        let connection = |option: &str| {
+            self.get_header("Connection").is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|item| item.trim().eq_ignore_ascii_case(option))
+            })
+        };
+
+        match self.version {
+            Version::Http1_0 => connection("keep-alive"),
+            Version::Http1_1 => !connection("close"),
+        }
     }
 
     pub fn get_cookie(&self, key: &str) -> Option<&RequestCookie> {
         self.cookies.iter().find(|cookie| cookie.key == key)
     }
+
+    /// Returns the value captured by the matched route for the given path parameter.
+    ///
+    /// Every captured value is also available in [`Request::params`], in pattern order.
+    pub fn param(&self, name: &str) -> Option<&String> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the application state of the given type registered with
+    /// [`RouterBuilder::with_state`](crate::router::RouterBuilder::with_state).
+    ///
+    /// Routes mounted with [`RouterBuilder::nest`](crate::router::RouterBuilder::nest) see the
+    /// state of their own router first, then the state of the routers they are nested in.
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state.iter().find_map(|state| state.get())
+    }
 }
 
 impl TryFrom<&mut TcpStream> for Request {
     type Error = &'static str;
 
     fn try_from(value: &mut TcpStream) -> Result<Self, Self::Error> {
-        let mut reader = BufReader::new(value);
+        Self::read_from(&mut BufReader::new(value), &Limits::default())
+    }
+}
 
-        // Raw header parsing from TcpStream
+impl Request {
+    /// Reads a request from the stream, failing with [`HEADERS_TOO_LARGE`] or [`BODY_TOO_LARGE`]
+    /// when it exceeds the limits.
+    ///
+    /// Requests whose body length is ambiguous, with a `Transfer-Encoding` or with several
+    /// `Content-Length` headers, are rejected since the end of the request cannot be found.
+    ///
+    /// Nothing past the end of the request is consumed, so the reader can be kept to read the
+    /// next request of a persistent connection.
+    pub fn read_from<R: BufRead>(reader: &mut R, limits: &Limits) -> Result<Self, &'static str> {
+        let (request, content_length) = Self::read_head(reader, limits)?;
+
+        let mut body = vec![0; content_length];
+        reader
+            .read_exact(&mut body)
+            .map_err(|_| CONNECTION_CLOSED)?;
+
+        request.with_body(body)
+    }
+
+    /// Reads the request line and headers, returning the request without its body along with the
+    /// length of the body announced by `Content-Length`.
+    pub(crate) fn read_head<R: BufRead>(
+        reader: &mut R,
+        limits: &Limits,
+    ) -> Result<(Self, usize), &'static str> {
+        // Raw header parsing from the stream
         let header = {
             let mut header = Vec::new();
+            let mut remaining = limits.max_header_bytes;
 
             loop {
                 let mut line = String::new();
-                reader.read_line(&mut line).unwrap();
+                let read = match reader.take(remaining as u64).read_line(&mut line) {
+                    Ok(read) => read,
+                    Err(e) if e.kind() == ErrorKind::InvalidData => {
+                        return Err("Invalid header encoding")
+                    }
+                    Err(_) => return Err(CONNECTION_CLOSED),
+                };
+
+                if !line.ends_with('\n') {
+                    return match read == remaining {
+                        true => Err(HEADERS_TOO_LARGE),
+                        false => Err(CONNECTION_CLOSED),
+                    };
+                }
+                remaining -= read;
+
                 if line == "\r\n" {
                     break;
                 }
@@ -54,7 +171,7 @@ impl TryFrom<&mut TcpStream> for Request {
 
         // Parsing of request line
         let (method, uri, version) = {
-            let request_line = header.get(0).ok_or("Empty request")?;
+            let request_line = header.first().ok_or("Empty request")?;
             let mut tokens = request_line.split_whitespace();
 
             let method = tokens.next().ok_or("No method found")?.try_into()?;
@@ -66,29 +183,46 @@ impl TryFrom<&mut TcpStream> for Request {
             (method, uri, version)
         };
 
-        let headers: HashMap<String, String> = header
+        let fields = header
             .into_iter()
             .skip(1)
-            .map_while(|line| {
-                let mut tokens = line.splitn(2, ':');
-
-                let key = if let Some(key) = tokens.next() {
-                    key
-                } else {
-                    return None;
-                };
-
-                let value = if let Some(value) = tokens.next() {
-                    value.trim()
-                } else {
-                    return None;
-                };
+            .map(|line| {
+                let (key, value) = line.split_once(':').ok_or("Invalid header")?;
+                if key.is_empty() || key.contains(char::is_whitespace) {
+                    return Err("Invalid header");
+                }
 
-                Some((key.to_owned(), value.to_owned()))
+                Ok((key.to_owned(), value.trim().to_owned()))
             })
-            .collect();
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // A body whose length the client and a proxy in front of the server could disagree on
+        // would let the rest of it be read as another request
+        let field = |name: &'static str| {
+            fields
+                .iter()
+                .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+        };
+        if field("Transfer-Encoding").next().is_some() {
+            return Err(UNSUPPORTED_TRANSFER_ENCODING);
+        }
+        let mut content_lengths = field("Content-Length").map(|(_, value)| value);
+        let content_length = match (content_lengths.next(), content_lengths.next()) {
+            (None, _) => 0,
+            (Some(_), Some(_)) => return Err("Duplicate Content-Length header"),
+            // Unlike parse, digits only, without a sign
+            (Some(value), None) if value.bytes().all(|byte| byte.is_ascii_digit()) => value
+                .parse::<usize>()
+                .map_err(|_| "Invalid Content-Length header")?,
+            (Some(_), None) => return Err("Invalid Content-Length header"),
+        };
+        if content_length > limits.max_body_bytes {
+            return Err(BODY_TOO_LARGE);
+        }
+
+        let headers: HashMap<String, String> = fields.into_iter().collect();
 
-        let cookies = match headers.get("Cookie") {
+        let cookies = match get_header(&headers, "Cookie") {
             Some(cookies) => cookies
                 .split("; ")
                 .map_while(|cookie| {
@@ -112,37 +246,32 @@ impl TryFrom<&mut TcpStream> for Request {
             None => vec![],
         };
 
-        let body = match headers.get("Content-Length") {
-            Some(content_length) => {
-                let content_length = content_length.parse::<u32>().unwrap();
-                if content_length == 0 {
-                    return Ok(Self {
-                        method,
-                        uri,
-                        version,
-                        headers,
-                        body: None,
-                        cookies,
-                    });
-                }
-
-                let mut body = vec![0; content_length as usize];
-                reader.read_exact(&mut body).unwrap();
-
-                let body = String::from_utf8(body).unwrap();
-
-                Some(body)
-            }
-            None => None,
-        };
-
-        Ok(Self {
+        let request = Self {
             method,
             uri,
             version,
             headers,
-            body,
+            body: None,
             cookies,
+            params: Vec::new(),
+            peer: None,
+            state: Vec::new(),
+        };
+
+        Ok((request, content_length))
+    }
+
+    /// Sets the body read after the head of the request, an empty body being none.
+    pub(crate) fn with_body(self, body: Vec<u8>) -> Result<Self, &'static str> {
+        if body.is_empty() {
+            return Ok(self);
+        }
+
+        let body = String::from_utf8(body).map_err(|_| "Invalid body encoding")?;
+
+        Ok(Self {
+            body: Some(body),
+            ..self
         })
     }
 }
@@ -157,7 +286,7 @@ impl TryFrom<&str> for Request {
             .collect::<Vec<_>>();
 
         let (method, uri, version) = {
-            let request_line = header.get(0).ok_or("Empty request")?;
+            let request_line = header.first().ok_or("Empty request")?;
             let mut tokens = request_line.split_whitespace();
 
             let method = tokens.next().ok_or("Empty request")?.try_into()?;
@@ -175,11 +304,7 @@ impl TryFrom<&str> for Request {
             .map_while(|line| {
                 let mut tokens = line.splitn(2, ':');
 
-                let key = if let Some(key) = tokens.next() {
-                    key
-                } else {
-                    return None;
-                };
+                let key = tokens.next()?;
 
                 let value = if let Some(value) = tokens.next() {
                     value.trim()
@@ -191,7 +316,7 @@ impl TryFrom<&str> for Request {
             })
             .collect::<HashMap<_, _>>();
 
-        let cookies = match headers.get("Cookie") {
+        let cookies = match get_header(&headers, "Cookie") {
             Some(cookies) => cookies
                 .split("; ")
                 .map_while(|cookie| {
@@ -215,7 +340,7 @@ impl TryFrom<&str> for Request {
             None => vec![],
         };
 
-        let body = match headers.get("Content-Length") {
+        let body = match get_header(&headers, "Content-Length") {
             Some(content_length) => {
                 let content_length = content_length.parse::<u32>().unwrap();
                 if content_length == 0 {
@@ -226,6 +351,9 @@ impl TryFrom<&str> for Request {
                         headers,
                         body: None,
                         cookies,
+                        params: Vec::new(),
+                        peer: None,
+                        state: Vec::new(),
                     });
                 }
 
@@ -246,13 +374,32 @@ impl TryFrom<&str> for Request {
             headers,
             body,
             cookies,
+            params: Vec::new(),
+            peer: None,
+            state: Vec::new(),
         })
     }
 }
 
+/// Returns the value of the header, matching its name case-insensitively.
+fn get_header<'a>(headers: &'a HashMap<String, String>, header: &str) -> Option<&'a String> {
+    headers.get(header).or_else(|| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(header))
+            .map(|(_, value)| value)
+    })
+}
+
 #[cfg(test)]
 mod test {
-    use crate::http::{request::Request, Method};
+    use crate::http::{
+        request::{
+            Limits, Request, BODY_TOO_LARGE, CONNECTION_CLOSED, HEADERS_TOO_LARGE,
+            UNSUPPORTED_TRANSFER_ENCODING,
+        },
+        Method,
+    };
 
     #[test]
     fn parse_request() {
@@ -279,5 +426,74 @@ mod test {
 
         let request: Result<Request, _> = "POSt / HTTP/1.2".try_into();
         assert!(request.is_err());
+
+        let request = Request::try_from("GET / HTTP/1.1\r\nhost: localhost\r\n\r\n").unwrap();
+        assert_eq!(request.get_header("Host"), Some(&"localhost".to_string()));
+        assert!(request.contains_header("HOST"));
+    }
+
+    #[test]
+    fn read_with_limits() {
+        let limits = Limits {
+            max_header_bytes: 64,
+            max_body_bytes: 4,
+        };
+
+        let request = Request::read_from(
+            &mut &b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody"[..],
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(request.body, Some("body".to_string()));
+
+        let request = Request::read_from(
+            &mut &b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nbody!"[..],
+            &limits,
+        );
+        assert_eq!(request.unwrap_err(), BODY_TOO_LARGE);
+
+        let header = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", "a".repeat(64));
+        let request = Request::read_from(&mut header.as_bytes(), &limits);
+        assert_eq!(request.unwrap_err(), HEADERS_TOO_LARGE);
+
+        let request = Request::read_from(&mut &b"GET / HTTP/1.1\r\n"[..], &limits);
+        assert_eq!(request.unwrap_err(), CONNECTION_CLOSED);
+    }
+
+    #[test]
+    fn read_body_length() {
+        let limits = Limits::default();
+        let read = |request: &str| Request::read_from(&mut request.as_bytes(), &limits);
+
+        let request =
+            read("POST / HTTP/1.1\r\ncontent-length: 4\r\ncookie: id=42\r\n\r\nbody").unwrap();
+        assert_eq!(request.body, Some("body".to_string()));
+        assert_eq!(request.get_cookie("id").unwrap().value, "42");
+
+        let request = read("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n");
+        assert_eq!(request.unwrap_err(), UNSUPPORTED_TRANSFER_ENCODING);
+
+        for request in [
+            "POST / HTTP/1.1\r\nContent-Length: 4\r\ncontent-length: 4\r\n\r\nbody",
+            "POST / HTTP/1.1\r\nContent-Length: 4\r\nContent-Length: 0\r\n\r\nbody",
+            "POST / HTTP/1.1\r\nContent-Length: 4, 4\r\n\r\nbody",
+            "POST / HTTP/1.1\r\nContent-Length: +4\r\n\r\nbody",
+            "POST / HTTP/1.1\r\nContent-Length : 4\r\n\r\nbody",
+            "POST / HTTP/1.1\r\nX-Invalid\r\nContent-Length: 4\r\n\r\nbody",
+        ] {
+            assert!(read(request).is_err(), "{request:?}");
+        }
+    }
+
+    #[test]
+    fn keep_alive() {
+        let keep_alive = |request: &str| Request::try_from(request).unwrap().keep_alive();
+
+        assert!(keep_alive("GET / HTTP/1.1\r\n\r\n"));
+        assert!(!keep_alive("GET / HTTP/1.1\r\nConnection: Close\r\n\r\n"));
+        assert!(!keep_alive("GET / HTTP/1.0\r\n\r\n"));
+        assert!(keep_alive(
+            "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n"
+        ));
     }
 }
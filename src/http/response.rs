@@ -4,8 +4,45 @@ use super::cookie::Cookie;
 #[derive(Debug)]
 pub struct Response {
     status_code: u16,
+    headers: Vec<(String, String)>,
     body: Option<String>,
     cookies: Vec<Cookie>,
+    omit_body: bool,
+}
+
+impl Response {
+    pub fn status_code(&self) -> u16 {
+        self.status_code
+    }
+
+    /// Returns the value of the header, matching its name case-insensitively.
+    pub fn get_header(&self, header: &str) -> Option<&String> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(header))
+            .map(|(_, value)| value)
+    }
+
+    pub fn with_header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Removes every value of the header, matching its name case-insensitively.
+    pub fn without_header(mut self, key: &str) -> Self {
+        self.headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+        self
+    }
+
+    /// Drops the body from the serialized response while still advertising its `Content-Length`,
+    /// as required when answering a HEAD request.
+    pub fn without_body(self) -> Self {
+        Self {
+            omit_body: true,
+            ..self
+        }
+    }
 }
 
 #[derive(Default, Clone)]
@@ -16,6 +53,7 @@ pub struct StatusCode(u16);
 #[derive(Default)]
 pub struct ResponseBuilder<TStatusCode> {
     status_code: TStatusCode,
+    headers: Vec<(String, String)>,
     body: Option<String>,
     cookies: Vec<Cookie>,
 }
@@ -28,6 +66,7 @@ impl ResponseBuilder<NoStatusCode> {
     pub fn with_status_code(self, status_code: u16) -> ResponseBuilder<StatusCode> {
         ResponseBuilder {
             status_code: StatusCode(status_code),
+            headers: self.headers,
             body: self.body,
             cookies: self.cookies,
         }
@@ -36,6 +75,16 @@ impl ResponseBuilder<NoStatusCode> {
     pub fn empty(self) -> ResponseBuilder<StatusCode> {
         ResponseBuilder {
             status_code: StatusCode(200),
+            headers: self.headers,
+            body: None,
+            cookies: self.cookies,
+        }
+    }
+
+    pub fn bad_request(self) -> ResponseBuilder<StatusCode> {
+        ResponseBuilder {
+            status_code: StatusCode(400),
+            headers: self.headers,
             body: None,
             cookies: self.cookies,
         }
@@ -44,6 +93,16 @@ impl ResponseBuilder<NoStatusCode> {
     pub fn not_found(self) -> ResponseBuilder<StatusCode> {
         ResponseBuilder {
             status_code: StatusCode(404),
+            headers: self.headers,
+            body: None,
+            cookies: self.cookies,
+        }
+    }
+
+    pub fn method_not_allowed(self) -> ResponseBuilder<StatusCode> {
+        ResponseBuilder {
+            status_code: StatusCode(405),
+            headers: self.headers,
             body: None,
             cookies: self.cookies,
         }
@@ -52,6 +111,7 @@ impl ResponseBuilder<NoStatusCode> {
     pub fn internal_server_error(self) -> ResponseBuilder<StatusCode> {
         ResponseBuilder {
             status_code: StatusCode(500),
+            headers: self.headers,
             body: self.body,
             cookies: self.cookies,
         }
@@ -59,9 +119,15 @@ impl ResponseBuilder<NoStatusCode> {
 }
 
 impl<TStatusCode> ResponseBuilder<TStatusCode> {
+    pub fn with_header(mut self, key: &str, value: &str) -> ResponseBuilder<TStatusCode> {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
     pub fn with_body(self, body: &str) -> ResponseBuilder<TStatusCode> {
         ResponseBuilder {
             status_code: self.status_code,
+            headers: self.headers,
             body: Some(body.to_string()),
             cookies: self.cookies,
         }
@@ -70,6 +136,7 @@ impl<TStatusCode> ResponseBuilder<TStatusCode> {
     pub fn with_cookies(self, cookies: Vec<Cookie>) -> ResponseBuilder<TStatusCode> {
         ResponseBuilder {
             status_code: self.status_code,
+            headers: self.headers,
             body: self.body,
             cookies,
         }
@@ -80,19 +147,132 @@ impl ResponseBuilder<StatusCode> {
     pub fn build(self) -> Response {
         Response {
             status_code: self.status_code.0,
+            headers: self.headers,
             body: self.body,
             cookies: self.cookies,
+            omit_body: false,
+        }
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc9110#section-15
+pub(crate) fn reason_phrase(status_code: u16) -> &'static str {
+    match status_code {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        203 => "Non-Authoritative Information",
+        204 => "No Content",
+        205 => "Reset Content",
+        206 => "Partial Content",
+        300 => "Multiple Choices",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        402 => "Payment Required",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        407 => "Proxy Authentication Required",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Content Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        417 => "Expectation Failed",
+        421 => "Misdirected Request",
+        422 => "Unprocessable Content",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        505 => "HTTP Version Not Supported",
+        _ => "",
+    }
+}
+
+/// Conversion of a handler's return value into a [`Response`].
+///
+/// Implemented for responses, string bodies answered with a 200, status codes answered with an
+/// empty body, `(status code, body)` tuples and results whose both variants implement it, so that
+/// handlers can use `?` to return early with an error response.
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl IntoResponse for ResponseBuilder<StatusCode> {
+    fn into_response(self) -> Response {
+        self.build()
+    }
+}
+
+impl IntoResponse for () {
+    fn into_response(self) -> Response {
+        ResponseBuilder::new().empty().build()
+    }
+}
+
+impl IntoResponse for &str {
+    fn into_response(self) -> Response {
+        ResponseBuilder::new().empty().with_body(self).build()
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Response {
+        self.as_str().into_response()
+    }
+}
+
+impl IntoResponse for u16 {
+    fn into_response(self) -> Response {
+        ResponseBuilder::new().with_status_code(self).build()
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (u16, T) {
+    fn into_response(self) -> Response {
+        Response {
+            status_code: self.0,
+            ..self.1.into_response()
+        }
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(e) => e.into_response(),
         }
     }
 }
 
 impl From<&Response> for String {
     fn from(value: &Response) -> Self {
-        let status_code = match value.status_code {
-            200 => "OK",
-            404 => "Not Found",
-            _ => "Internal Server Error",
-        };
+        let status_code = reason_phrase(value.status_code);
 
         let content_length = match &value.body {
             Some(body) => body.len(),
@@ -104,13 +284,17 @@ impl From<&Response> for String {
             value.status_code, status_code, content_length
         );
 
+        let response = value.headers.iter().fold(response, |acc, (key, value)| {
+            format!("{}{}: {}\r\n", acc, key, value)
+        });
+
         let response = value.cookies.iter().fold(response, |acc, cookie| {
             format!("{}{}", acc, String::from(cookie))
         });
 
         match &value.body {
-            Some(body) => format!("{}\r\n{}", response, body),
-            None => format!("{}\r\n", response),
+            Some(body) if !value.omit_body => format!("{}\r\n{}", response, body),
+            _ => format!("{}\r\n", response),
         }
     }
 }
@@ -158,4 +342,55 @@ mod tests {
 
         assert_eq!(String::from(&response), expected);
     }
+
+    #[test]
+    fn test_into_response() {
+        assert_eq!(
+            String::from(&"Hello!".into_response()),
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nHello!"
+        );
+
+        assert_eq!(
+            String::from(&204.into_response()),
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n"
+        );
+
+        assert_eq!(
+            String::from(&(201, "Created!".to_string()).into_response()),
+            "HTTP/1.1 201 Created\r\nContent-Length: 8\r\n\r\nCreated!"
+        );
+
+        let result: Result<&str, (u16, &str)> = Err((403, "Forbidden"));
+        assert_eq!(
+            String::from(&result.into_response()),
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 9\r\n\r\nForbidden"
+        );
+    }
+
+    #[test]
+    fn test_without_body() {
+        let response = ResponseBuilder::new()
+            .empty()
+            .with_body("Hello!")
+            .build()
+            .without_body();
+
+        assert_eq!(
+            String::from(&response),
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_headers() {
+        let response = ResponseBuilder::new()
+            .method_not_allowed()
+            .with_header("Allow", "GET, POST")
+            .build();
+
+        let expected =
+            "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nAllow: GET, POST\r\n\r\n";
+
+        assert_eq!(String::from(&response), expected);
+    }
 }
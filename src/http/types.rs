@@ -1,4 +1,6 @@
-use super::{Method, Uri, Version};
+use std::fmt;
+
+use super::{Method, Peer, Uri, Version};
 
 // https://datatracker.ietf.org/doc/html/rfc2616#section-5.1.1
 impl TryFrom<&str> for Method {
@@ -19,6 +21,22 @@ impl TryFrom<&str> for Method {
     }
 }
 
+impl Method {
+    /// Returns the method token as it appears on the request line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Connect => "CONNECT",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+        }
+    }
+}
+
 impl TryFrom<&str> for Version {
     type Error = &'static str;
 
@@ -43,19 +61,7 @@ impl TryFrom<&str> for Uri {
             Some(query) => query
                 .split('&')
                 .map_while(|pair| {
-                    let mut tokens = pair.splitn(2, '=');
-
-                    let key = if let Some(key) = tokens.next() {
-                        key
-                    } else {
-                        return None;
-                    };
-
-                    let value = if let Some(value) = tokens.next() {
-                        value
-                    } else {
-                        return None;
-                    };
+                    let (key, value) = pair.split_once('=')?;
 
                     Some((key.to_owned(), value.to_owned()))
                 })
@@ -67,6 +73,19 @@ impl TryFrom<&str> for Uri {
     }
 }
 
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Peer::Tcp(address) => write!(f, "{address}"),
+            Peer::Unix(Some(credentials)) => match credentials.pid {
+                Some(pid) => write!(f, "unix:uid={},pid={pid}", credentials.uid),
+                None => write!(f, "unix:uid={}", credentials.uid),
+            },
+            Peer::Unix(None) => write!(f, "unix"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::http::Method;
@@ -1,3 +1,5 @@
 pub mod core;
+pub mod extract;
 pub mod http;
 pub mod router;
+pub mod state;
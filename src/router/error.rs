@@ -0,0 +1,127 @@
+use std::{any::Any, fmt};
+
+use crate::http::Method;
+
+/// A failure while serving a request, mapped to a response by the error handler of the
+/// [`Router`](super::Router).
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    /// The request could not be parsed.
+    Parse(&'static str),
+    /// A handler or middleware panicked, carrying the panic message.
+    Panic(String),
+}
+
+impl RequestError {
+    pub(crate) fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "Unknown panic".to_string(),
+            },
+        };
+
+        RequestError::Panic(message)
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Parse(e) => write!(f, "Invalid request: {e}"),
+            RequestError::Panic(e) => write!(f, "Handler panicked: {e}"),
+        }
+    }
+}
+
+/// A failure while generating the URL of a named route with
+/// [`Router::url_for`](super::Router::url_for).
+#[derive(Debug, PartialEq)]
+pub enum UrlError {
+    /// No route is registered under the given name.
+    UnknownRoute(String),
+    /// The route pattern contains a parameter which was not provided.
+    MissingParam(String),
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::UnknownRoute(name) => write!(f, "No route named {name}"),
+            UrlError::MissingParam(name) => write!(f, "Missing route parameter {name}"),
+        }
+    }
+}
+
+/// A misconfiguration of the routing table detected by
+/// [`RouterBuilder::try_build`](super::RouterBuilder::try_build).
+#[derive(Debug, PartialEq)]
+pub enum Conflict {
+    /// The route path is not a valid pattern.
+    InvalidPattern { path: String, reason: &'static str },
+    /// The route has the same method and path as a previously registered one.
+    DuplicateRoute { method: Method, path: String },
+    /// The route matches the same paths with the same method as a previously registered one,
+    /// using different parameter names.
+    AmbiguousRoute {
+        method: Method,
+        path: String,
+        previous: String,
+    },
+    /// The route name is already used by another route.
+    DuplicateName { name: String },
+    /// The router nested under the prefix has virtual hosts, which only apply at the top level.
+    NestedHosts { prefix: String },
+    /// The router nested under the prefix serves an OpenAPI document, which would not include the
+    /// prefix in its paths.
+    NestedOpenApi { prefix: String },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::InvalidPattern { path, reason } => {
+                write!(f, "Invalid route path {path}: {reason}")
+            }
+            Conflict::DuplicateRoute { method, path } => {
+                write!(f, "Duplicate route {} {path}", method.as_str())
+            }
+            Conflict::AmbiguousRoute {
+                method,
+                path,
+                previous,
+            } => write!(
+                f,
+                "Route {} {path} is ambiguous with {previous}",
+                method.as_str()
+            ),
+            Conflict::DuplicateName { name } => write!(f, "Duplicate route name {name}"),
+            Conflict::NestedHosts { prefix } => {
+                write!(f, "Router nested under {prefix} has virtual hosts")
+            }
+            Conflict::NestedOpenApi { prefix } => {
+                write!(f, "Router nested under {prefix} serves an OpenAPI document")
+            }
+        }
+    }
+}
+
+/// Every conflict found in the routing table when building a [`Router`](super::Router).
+#[derive(Debug, PartialEq)]
+pub struct BuildError {
+    pub conflicts: Vec<Conflict>,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid routing table:")?;
+        for conflict in &self.conflicts {
+            write!(f, "\n  - {conflict}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,132 @@
+//! Common route guards, see [`Route::with_guard`](super::Route::with_guard).
+
+use crate::http::request::Request;
+
+/// Accepts requests whose path parameter is made of ASCII digits only.
+pub fn numeric(param: &str) -> impl Fn(&Request) -> bool + Send + Sync + 'static {
+    let param = param.to_string();
+
+    move |request| {
+        request
+            .param(&param)
+            .is_some_and(|value| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()))
+    }
+}
+
+/// Accepts requests whose path parameter can be parsed as a `T`, e.g. `u32` or `bool`.
+pub fn parses<T: std::str::FromStr>(
+    param: &str,
+) -> impl Fn(&Request) -> bool + Send + Sync + 'static {
+    let param = param.to_string();
+
+    move |request| {
+        request
+            .param(&param)
+            .is_some_and(|value| value.parse::<T>().is_ok())
+    }
+}
+
+/// Accepts requests whose path parameter matches the regular expression.
+///
+/// Panics if the expression is invalid. Requires the `regex` feature.
+#[cfg(feature = "regex")]
+pub fn regex(param: &str, expression: &str) -> impl Fn(&Request) -> bool + Send + Sync + 'static {
+    let param = param.to_string();
+    let expression = regex::Regex::new(expression).unwrap();
+
+    move |request| {
+        request
+            .param(&param)
+            .is_some_and(|value| expression.is_match(value))
+    }
+}
+
+/// Accepts requests carrying the header with the given value, either as its whole value or as one
+/// of its comma separated items, ignoring parameters such as `;q=0.9`. Values are compared
+/// case-insensitively.
+pub fn header(name: &str, value: &str) -> impl Fn(&Request) -> bool + Send + Sync + 'static {
+    let name = name.to_string();
+    let value = value.to_string();
+
+    move |request| {
+        request.get_header(&name).is_some_and(|header| {
+            header.eq_ignore_ascii_case(&value)
+                || header.split(',').any(|item| {
+                    let item = item.split(';').next().unwrap_or_default();
+                    item.trim().eq_ignore_ascii_case(&value)
+                })
+        })
+    }
+}
+
+/// Accepts requests whose query string contains the attribute with the given value.
+pub fn query(key: &str, value: &str) -> impl Fn(&Request) -> bool + Send + Sync + 'static {
+    let key = key.to_string();
+    let value = value.to_string();
+
+    move |request| {
+        request
+            .uri
+            .attributes
+            .iter()
+            .any(|(k, v)| *k == key && *v == value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::request::Request;
+
+    fn request(line: &str, params: &[(&str, &str)]) -> Request {
+        let mut request = Request::try_from(line).unwrap();
+        request.params = params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        request
+    }
+
+    #[test]
+    fn param_guards() {
+        let numeric = super::numeric("id");
+        assert!(numeric(&request("GET / HTTP/1.0", &[("id", "42")])));
+        assert!(!numeric(&request("GET / HTTP/1.0", &[("id", "-42")])));
+        assert!(!numeric(&request("GET / HTTP/1.0", &[("id", "me")])));
+        assert!(!numeric(&request("GET / HTTP/1.0", &[])));
+
+        let parses = super::parses::<i8>("id");
+        assert!(parses(&request("GET / HTTP/1.0", &[("id", "-42")])));
+        assert!(!parses(&request("GET / HTTP/1.0", &[("id", "420")])));
+    }
+
+    #[test]
+    fn request_guards() {
+        let header = super::header("Accept", "application/json");
+        assert!(header(&request(
+            "GET / HTTP/1.0\r\nAccept: text/html, application/json;q=0.9\r\n\r\n",
+            &[]
+        )));
+        assert!(!header(&request(
+            "GET / HTTP/1.0\r\nAccept: text/html\r\n\r\n",
+            &[]
+        )));
+
+        let query = super::query("format", "csv");
+        assert!(query(&request("GET /?format=csv HTTP/1.0", &[])));
+        assert!(!query(&request("GET /?format=json HTTP/1.0", &[])));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_guard() {
+        let regex = super::regex("slug", "^[a-z0-9-]+$");
+        assert!(regex(&request(
+            "GET / HTTP/1.0",
+            &[("slug", "hello-world")]
+        )));
+        assert!(!regex(&request(
+            "GET / HTTP/1.0",
+            &[("slug", "Hello World")]
+        )));
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+/// A virtual host pattern, either an exact name such as `example.com` or a wildcard such as
+/// `*.example.com` matching any of its subdomains.
+#[derive(Debug, PartialEq)]
+pub enum HostPattern {
+    Exact(String),
+    Wildcard(String),
+}
+
+impl HostPattern {
+    /// Matches a host name, without port, ignoring case.
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+
+        match self {
+            HostPattern::Exact(name) => host == *name,
+            HostPattern::Wildcard(suffix) => host
+                .strip_suffix(suffix.as_str())
+                .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        }
+    }
+}
+
+impl From<&str> for HostPattern {
+    fn from(value: &str) -> Self {
+        let value = value.to_ascii_lowercase();
+
+        match value.strip_prefix("*.") {
+            Some(suffix) => HostPattern::Wildcard(suffix.to_string()),
+            None => HostPattern::Exact(value),
+        }
+    }
+}
+
+impl fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostPattern::Exact(name) => write!(f, "{name}"),
+            HostPattern::Wildcard(suffix) => write!(f, "*.{suffix}"),
+        }
+    }
+}
+
+/// Strips the port from the value of a `Host` header, keeping the brackets of IPv6 addresses.
+pub fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        };
+    }
+
+    match host.split_once(':') {
+        Some((name, _)) => name,
+        None => host,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{strip_port, HostPattern};
+
+    #[test]
+    fn match_host() {
+        let pattern = HostPattern::from("Example.com");
+        assert!(pattern.matches("example.com"));
+        assert!(pattern.matches("EXAMPLE.COM"));
+        assert!(!pattern.matches("www.example.com"));
+
+        let pattern = HostPattern::from("*.example.com");
+        assert!(pattern.matches("www.example.com"));
+        assert!(pattern.matches("a.b.example.com"));
+        assert!(!pattern.matches("example.com"));
+        assert!(!pattern.matches("wwwexample.com"));
+    }
+
+    #[test]
+    fn strip_host_port() {
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+    }
+}
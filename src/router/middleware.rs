@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use crate::http::{request::Request, response::Response};
+
+/// Code running around a handler, able to inspect the request, alter the response returned by
+/// `next`, or short-circuit the chain by returning its own response without calling `next`.
+pub trait Middleware: Send + Sync {
+    fn handle(&self, request: &Request, next: Next<'_>) -> Response;
+
+    /// The name of the middleware listed by [`Router::routes`](super::Router::routes), its type
+    /// name by default.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The remainder of a middleware chain, ending with the handler.
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn Middleware>],
+    handler: &'a (dyn Fn(&Request) -> Response + Send + Sync),
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        middleware: &'a [Arc<dyn Middleware>],
+        handler: &'a (dyn Fn(&Request) -> Response + Send + Sync),
+    ) -> Self {
+        Self {
+            middleware,
+            handler,
+        }
+    }
+
+    /// Runs the next middleware in the chain, or the handler once the chain is exhausted.
+    pub fn run(self, request: &Request) -> Response {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => middleware.handle(request, Next::new(rest, self.handler)),
+            None => (self.handler)(request),
+        }
+    }
+}
+
+/// A middleware built from a closure, see [`from_fn`].
+pub struct FromFn<F>(F);
+
+impl<F> Middleware for FromFn<F>
+where
+    F: Fn(&Request, Next<'_>) -> Response + Send + Sync,
+{
+    fn handle(&self, request: &Request, next: Next<'_>) -> Response {
+        (self.0)(request, next)
+    }
+}
+
+/// Creates a middleware from a closure taking the request and the rest of the chain.
+pub fn from_fn<F>(f: F) -> FromFn<F>
+where
+    F: Fn(&Request, Next<'_>) -> Response + Send + Sync,
+{
+    FromFn(f)
+}
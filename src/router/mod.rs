@@ -0,0 +1,1455 @@
+pub mod error;
+pub mod guard;
+pub mod host;
+pub mod middleware;
+pub mod openapi;
+pub mod path;
+mod tree;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, OnceLock},
+};
+#[cfg(feature = "tokio")]
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    extract,
+    http::{
+        request::{self, Request},
+        response::{IntoResponse, Response, ResponseBuilder},
+        Method, Version,
+    },
+    state::State,
+};
+
+use self::{
+    error::{BuildError, Conflict, RequestError, UrlError},
+    host::HostPattern,
+    middleware::{Middleware, Next},
+    openapi::{Info, Operation},
+    path::Pattern,
+    tree::Tree,
+};
+
+/// A request handler, either a function or a closure capturing shared state.
+pub type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// A predicate a request must satisfy, in addition to its path and method, to be handled by a route.
+pub type Guard = Box<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// Maps a failure while serving a request to the response sent to the client.
+pub type ErrorHandler = Box<dyn Fn(&RequestError) -> Response + Send + Sync>;
+
+/// A request handler returning a future, awaited by the [`AsyncServer`](crate::core::AsyncServer).
+#[cfg(feature = "tokio")]
+pub(crate) type AsyncHandler =
+    Box<dyn Fn(&Request) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
+
+/// A route handled by the [`Router`].
+///
+/// The path may contain named parameters (`/users/:id`) and a trailing wildcard (`/static/*path`),
+/// whose percent-decoded values are available to the handler through [`Request::param`].
+pub struct Route {
+    pub path: String,
+    pub method: Method,
+    pub handler: Handler,
+    pub middleware: Vec<Arc<dyn Middleware>>,
+    pub name: Option<String>,
+    pub guards: Vec<Guard>,
+    pub operation: Option<Operation>,
+    /// The states of the routers the route was nested from, innermost first.
+    pub(crate) state: Vec<Arc<State>>,
+    #[cfg(feature = "tokio")]
+    pub(crate) async_handler: Option<AsyncHandler>,
+}
+
+impl Route {
+    /// Creates a route whose handler returns any value implementing [`IntoResponse`], such as a
+    /// `Result` allowing the use of `?`.
+    pub fn new<F, R>(path: &str, method: Method, handler: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        Self {
+            path: path.to_string(),
+            method,
+            handler: Box::new(move |request| handler(request).into_response()),
+            middleware: Vec::new(),
+            name: None,
+            guards: Vec::new(),
+            operation: None,
+            state: Vec::new(),
+            #[cfg(feature = "tokio")]
+            async_handler: None,
+        }
+    }
+
+    /// Adds a guard the request must satisfy for the route to handle it, see [`guard`] for the
+    /// common ones. When a guard rejects the request, the next candidate route is tried.
+    ///
+    /// Guards run after the path parameters are captured, so they can inspect [`Request::param`].
+    pub fn with_guard<G>(mut self, guard: G) -> Self
+    where
+        G: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.guards.push(Box::new(guard));
+        self
+    }
+
+    /// Creates a route whose handler declares its inputs as [`extract`] arguments, e.g.
+    /// `|Path(id): Path<u32>| ...`, which are extracted before it runs. Requests failing the
+    /// extraction are answered with a 400 describing the failure.
+    pub fn typed<H, Args>(path: &str, method: Method, handler: H) -> Self
+    where
+        H: extract::Handler<Args>,
+    {
+        Self::new(path, method, move |request| handler.call(request))
+    }
+
+    /// Creates a route whose handler returns a future. The future cannot borrow the request, so
+    /// the handler extracts what it needs before returning it.
+    ///
+    /// The [`AsyncServer`](crate::core::AsyncServer) awaits the future on the task of the
+    /// connection, without holding a thread, unless middleware apply to the route: the
+    /// middleware being synchronous, the route then runs on the blocking thread pool like sync
+    /// handlers. Elsewhere, such as under [`Server`](crate::core::Server), the future runs on a
+    /// shared runtime while the thread waits for it.
+    ///
+    /// Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub fn new_async<F, Fut, R>(path: &str, method: Method, handler: F) -> Self
+    where
+        F: Fn(&Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        let handler = Arc::new(handler);
+        let blocking = handler.clone();
+
+        Self {
+            async_handler: Some(Box::new(move |request| {
+                let future = handler(request);
+                Box::pin(async move { future.await.into_response() })
+            })),
+            ..Self::new(path, method, move |request| {
+                let future = blocking(request);
+                crate::core::block_on(async move { future.await.into_response() })
+            })
+        }
+    }
+
+    /// Names the route so its URL can be generated with [`Router::url_for`].
+    pub fn with_name(self, name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..self
+        }
+    }
+
+    /// Adds a middleware running only for this route, after the global middleware of the router.
+    pub fn with_middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+}
+
+/// A registered route as listed by [`Router::routes`].
+#[derive(Debug, PartialEq)]
+pub struct RouteInfo<'a> {
+    /// The virtual host serving the route, `None` for the default host.
+    pub host: Option<&'a HostPattern>,
+    pub method: Method,
+    pub path: &'a str,
+    pub name: Option<&'a str>,
+    /// The names of the middleware running for the route, in execution order.
+    pub middleware: Vec<&'a str>,
+    pub guards: usize,
+}
+
+pub struct Router {
+    routes: Vec<(Pattern, Route)>,
+    tree: Tree,
+    names: HashMap<String, usize>,
+    state: Arc<State>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    fallback: Option<Handler>,
+    error_handler: Option<ErrorHandler>,
+    hosts: Vec<(HostPattern, Router)>,
+    /// Whether one of the routes serves the OpenAPI document of the router.
+    openapi: bool,
+}
+
+#[derive(Default)]
+pub struct RouterBuilder {
+    routes: Vec<Route>,
+    state: State,
+    middleware: Vec<Arc<dyn Middleware>>,
+    fallback: Option<Handler>,
+    error_handler: Option<ErrorHandler>,
+    hosts: Vec<(HostPattern, Router)>,
+    openapi: Option<(String, Info)>,
+    conflicts: Vec<Conflict>,
+}
+
+/// The outcome of matching a request against the routing table.
+enum Target<'a> {
+    Route(&'a Route),
+    Allowed(Vec<Method>),
+    NotFound,
+}
+
+impl RouterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_route(mut self, route: Route) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// Registers a value shared by every handler, accessible through [`Request::state`].
+    ///
+    /// One value is kept per type, so registering a second value of the same type replaces the first.
+    pub fn with_state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.state.insert(state);
+        self
+    }
+
+    /// Adds a middleware running for every request, including the ones not matching any route.
+    ///
+    /// Middleware run in registration order, the first one registered being the outermost.
+    pub fn with_middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sets the handler answering requests whose path matches no route, instead of an empty 404.
+    pub fn with_fallback<F, R>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.fallback = Some(Box::new(move |request| handler(request).into_response()));
+        self
+    }
+
+    /// Sets the handler mapping unparsable requests and panicking handlers to a response.
+    ///
+    /// By default parse errors are answered with a 400 carrying the error message and panics with
+    /// an empty 500.
+    pub fn with_error_handler<F, R>(mut self, handler: F) -> Self
+    where
+        F: Fn(&RequestError) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.error_handler = Some(Box::new(move |error| handler(error).into_response()));
+        self
+    }
+
+    /// Serves the requests whose `Host` header matches the pattern with another router.
+    ///
+    /// The pattern is either an exact host name such as `api.example.com` or a wildcard such as
+    /// `*.example.com` matching every subdomain, exact names taking precedence over wildcards.
+    /// Requests matching no virtual host are served by the routes of this builder, whose
+    /// middleware, fallback and error handlers do not apply to the virtual hosts.
+    pub fn with_host(mut self, pattern: &str, router: Router) -> Self {
+        self.hosts.push((HostPattern::from(pattern), router));
+        self
+    }
+
+    /// Serves the OpenAPI document of the router as JSON at the given path, see
+    /// [`Router::openapi`].
+    pub fn with_openapi(self, path: &str, info: Info) -> Self {
+        Self {
+            openapi: Some((path.to_string(), info)),
+            ..self
+        }
+    }
+
+    /// Mounts every route of `router` under the given path prefix, e.g. `/api/v1`.
+    ///
+    /// The global middleware of the nested router only run for its own routes, before their
+    /// route-level middleware. Its state stays attached to its routes, which see it before the
+    /// state of this builder, while its fallback and error handlers are ignored.
+    ///
+    /// Virtual hosts cannot be nested: building fails with [`Conflict::NestedHosts`] if the
+    /// nested router has any. Neither can a router serving its OpenAPI document, whose paths would
+    /// miss the prefix: building fails with [`Conflict::NestedOpenApi`], use
+    /// [`RouterBuilder::with_openapi`] on the outer builder instead.
+    pub fn nest(mut self, prefix: &str, router: Router) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+
+        if !router.hosts.is_empty() {
+            self.conflicts.push(Conflict::NestedHosts {
+                prefix: prefix.to_string(),
+            });
+        }
+        if router.openapi {
+            self.conflicts.push(Conflict::NestedOpenApi {
+                prefix: prefix.to_string(),
+            });
+        }
+
+        for (_, mut route) in router.routes {
+            route.path = match route.path.as_str() {
+                "/" if !prefix.is_empty() => prefix.to_string(),
+                path => format!("{prefix}{path}"),
+            };
+
+            route.middleware = router
+                .middleware
+                .iter()
+                .cloned()
+                .chain(route.middleware)
+                .collect();
+            route.state.push(router.state.clone());
+
+            self.routes.push(route);
+        }
+
+        self
+    }
+
+    /// Builds the router, panicking with the list of conflicts if the routing table is invalid.
+    ///
+    /// See [`RouterBuilder::try_build`] for the checks performed.
+    pub fn build(self) -> Router {
+        match self.try_build() {
+            Ok(router) => router,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Builds the router, validating the routing table.
+    ///
+    /// Fails with every conflict found: malformed patterns, routes shadowed by a previous route
+    /// without guards matching the same paths with the same method, route names used more than
+    /// once, and nested virtual hosts or OpenAPI documents.
+    pub fn try_build(mut self) -> Result<Router, BuildError> {
+        // The document is generated once every route, including its own, is registered
+        let document = Arc::new(OnceLock::<String>::new());
+        if let Some((path, _)) = &self.openapi {
+            let document = document.clone();
+            self.routes
+                .push(Route::new(path, Method::Get, move |_request| {
+                    ResponseBuilder::new()
+                        .empty()
+                        .with_header("Content-Type", "application/json")
+                        .with_body(document.get().map_or("", String::as_str))
+                        .build()
+                }));
+        }
+
+        let mut conflicts = self.conflicts;
+        let mut tree = Tree::default();
+        let mut names = HashMap::new();
+        let mut shapes: HashMap<(Method, String), String> = HashMap::new();
+        let mut routes = Vec::with_capacity(self.routes.len());
+
+        for route in self.routes {
+            let pattern = match Pattern::try_from(route.path.as_str()) {
+                Ok(pattern) => pattern,
+                Err(reason) => {
+                    conflicts.push(Conflict::InvalidPattern {
+                        path: route.path,
+                        reason,
+                    });
+                    continue;
+                }
+            };
+
+            match shapes.get(&(route.method, pattern.shape())) {
+                Some(previous) if *previous == route.path => {
+                    conflicts.push(Conflict::DuplicateRoute {
+                        method: route.method,
+                        path: route.path.clone(),
+                    });
+                }
+                Some(previous) => {
+                    conflicts.push(Conflict::AmbiguousRoute {
+                        method: route.method,
+                        path: route.path.clone(),
+                        previous: previous.to_owned(),
+                    });
+                }
+                None if route.guards.is_empty() => {
+                    shapes.insert((route.method, pattern.shape()), route.path.clone());
+                }
+                None => {}
+            }
+
+            if let Some(name) = &route.name {
+                if names.insert(name.to_owned(), routes.len()).is_some() {
+                    conflicts.push(Conflict::DuplicateName {
+                        name: name.to_owned(),
+                    });
+                }
+            }
+
+            tree.insert(&pattern, routes.len());
+            routes.push((pattern, route));
+        }
+
+        if !conflicts.is_empty() {
+            return Err(BuildError { conflicts });
+        }
+
+        if let Some((_, info)) = &self.openapi {
+            let _ = document.set(openapi::document(
+                info,
+                routes.iter().map(|(_, route)| route),
+            ));
+        }
+
+        Ok(Router {
+            routes,
+            tree,
+            names,
+            state: Arc::new(self.state),
+            middleware: self.middleware,
+            fallback: self.fallback,
+            error_handler: self.error_handler,
+            hosts: self.hosts,
+            openapi: self.openapi.is_some(),
+        })
+    }
+}
+
+impl Router {
+    /// Dispatches the request to the matching route through the middleware chain.
+    ///
+    /// HEAD requests without an explicit route run the GET handler, while OPTIONS requests without
+    /// an explicit route are answered with the methods allowed for the path. The body of every
+    /// response to a HEAD request is dropped.
+    ///
+    /// HTTP/1.1 requests without a `Host` header are rejected as a parse error.
+    pub fn dispatch(&self, request: &mut Request) -> Response {
+        if request.version == Version::Http1_1 && !request.contains_header("Host") {
+            return self.handle_error(&RequestError::Parse("Missing Host header"));
+        }
+
+        if let Some(router) = self.virtual_host(request) {
+            return router.dispatch(request);
+        }
+
+        // Guards run while resolving the route, so they are covered by the error handler too
+        let response = panic::catch_unwind(AssertUnwindSafe(|| {
+            let target = self.resolve(request);
+            request.state = match &target {
+                Target::Route(route) => self.states(route),
+                _ => vec![self.state.clone()],
+            };
+
+            let handler = |request: &Request| match &target {
+                Target::Route(route) => Next::new(&route.middleware, &route.handler).run(request),
+                Target::Allowed(allowed) => {
+                    let allow = allowed
+                        .iter()
+                        .map(Method::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    match request.method {
+                        Method::Options => ResponseBuilder::new()
+                            .empty()
+                            .with_header("Allow", &allow)
+                            .build(),
+                        _ => ResponseBuilder::new()
+                            .method_not_allowed()
+                            .with_header("Allow", &allow)
+                            .build(),
+                    }
+                }
+                Target::NotFound => match &self.fallback {
+                    Some(fallback) => fallback(request),
+                    None => ResponseBuilder::new().not_found().build(),
+                },
+            };
+
+            Next::new(&self.middleware, &handler).run(request)
+        }))
+        .unwrap_or_else(|payload| self.handle_error(&RequestError::from_panic(payload)));
+
+        match request.method {
+            Method::Head => response.without_body(),
+            _ => response,
+        }
+    }
+
+    /// Starts the async handler of the route matching the request, for the
+    /// [`AsyncServer`](crate::core::AsyncServer) to await it. Returns `None` when the request goes
+    /// through [`Router::dispatch`] instead: it is invalid, matches no async route, or middleware
+    /// apply to the route.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn dispatch_async(
+        &self,
+        request: &mut Request,
+    ) -> Option<impl Future<Output = Response> + Send + '_> {
+        if request.version == Version::Http1_1 && !request.contains_header("Host") {
+            return None;
+        }
+
+        let mut router = self;
+        while let Some(host) = router.virtual_host(request) {
+            router = host;
+        }
+
+        let future = match panic::catch_unwind(AssertUnwindSafe(|| router.resolve(request))) {
+            Ok(Target::Route(route)) => {
+                if !router.middleware.is_empty() || !route.middleware.is_empty() {
+                    return None;
+                }
+                let handler = route.async_handler.as_ref()?;
+                request.state = router.states(route);
+
+                panic::catch_unwind(AssertUnwindSafe(|| handler(request)))
+            }
+            Ok(_) => return None,
+            // A guard panicked
+            Err(payload) => Err(payload),
+        };
+
+        let method = request.method;
+
+        Some(async move {
+            let response = match future {
+                Ok(future) => crate::core::catch_unwind(future).await,
+                Err(payload) => Err(payload),
+            }
+            .unwrap_or_else(|payload| router.handle_error(&RequestError::from_panic(payload)));
+
+            match method {
+                Method::Head => response.without_body(),
+                _ => response,
+            }
+        })
+    }
+
+    /// Builds the path of the route registered under the given name, percent-encoding the values
+    /// of its parameters.
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlError> {
+        let index = self
+            .names
+            .get(name)
+            .ok_or_else(|| UrlError::UnknownRoute(name.to_string()))?;
+
+        self.routes[*index].0.format(params)
+    }
+
+    /// Lists every registered route in registration order, followed by the routes of each virtual
+    /// host. The table is also printed by the [`Display`](fmt::Display) implementation.
+    pub fn routes(&self) -> Vec<RouteInfo<'_>> {
+        let mut routes = self.route_infos(None);
+        for (pattern, router) in &self.hosts {
+            routes.extend(router.route_infos(Some(pattern)));
+        }
+
+        routes
+    }
+
+    fn route_infos<'a>(&'a self, host: Option<&'a HostPattern>) -> Vec<RouteInfo<'a>> {
+        self.routes
+            .iter()
+            .map(|(_, route)| RouteInfo {
+                host,
+                method: route.method,
+                path: &route.path,
+                name: route.name.as_deref(),
+                middleware: self
+                    .middleware
+                    .iter()
+                    .chain(&route.middleware)
+                    .map(|middleware| middleware.name())
+                    .collect(),
+                guards: route.guards.len(),
+            })
+            .collect()
+    }
+
+    /// Generates the OpenAPI 3 document describing the routes of the default host as JSON, using
+    /// the metadata attached with [`Route::with_operation`].
+    pub fn openapi(&self, info: &Info) -> String {
+        openapi::document(info, self.routes.iter().map(|(_, route)| route))
+    }
+
+    /// Maps the error to a response through the registered error handler.
+    ///
+    /// By default requests exceeding the size limits of the server are answered with a 431 or a
+    /// 413, requests with a `Transfer-Encoding` with a 501, other parse errors with a 400 and
+    /// panics with an empty 500.
+    pub fn handle_error(&self, error: &RequestError) -> Response {
+        if let Some(error_handler) = &self.error_handler {
+            return error_handler(error);
+        }
+
+        match error {
+            RequestError::Parse(e @ request::HEADERS_TOO_LARGE) => ResponseBuilder::new()
+                .with_status_code(431)
+                .with_body(e)
+                .build(),
+            RequestError::Parse(e @ request::BODY_TOO_LARGE) => ResponseBuilder::new()
+                .with_status_code(413)
+                .with_body(e)
+                .build(),
+            RequestError::Parse(e @ request::UNSUPPORTED_TRANSFER_ENCODING) => {
+                ResponseBuilder::new()
+                    .with_status_code(501)
+                    .with_body(e)
+                    .build()
+            }
+            RequestError::Parse(e) => ResponseBuilder::new().bad_request().with_body(e).build(),
+            RequestError::Panic(_) => ResponseBuilder::new().internal_server_error().build(),
+        }
+    }
+
+    /// Finds the router of the virtual host matching the `Host` header of the request.
+    fn virtual_host(&self, request: &Request) -> Option<&Router> {
+        let host = host::strip_port(request.get_header("Host")?);
+
+        self.hosts
+            .iter()
+            .filter(|(pattern, _)| matches!(pattern, HostPattern::Exact(_)))
+            .chain(
+                self.hosts
+                    .iter()
+                    .filter(|(pattern, _)| matches!(pattern, HostPattern::Wildcard(_))),
+            )
+            .find(|(pattern, _)| pattern.matches(host))
+            .map(|(_, router)| router)
+    }
+
+    /// Finds the route handling the request and stores the captured parameters in it.
+    fn resolve(&self, request: &mut Request) -> Target<'_> {
+        let found = self
+            .find(request, request.method)
+            .or_else(|| match request.method {
+                Method::Head => self.find(request, Method::Get),
+                _ => None,
+            });
+
+        if let Some(route) = found {
+            return Target::Route(route);
+        }
+
+        // Routes registered for the method whose guards all rejected the request
+        let guarded = self.tree.find(&request.uri.path, |routes, _| {
+            routes
+                .iter()
+                .map(|&index| self.routes[index].1.method)
+                .any(|method| {
+                    method == request.method
+                        || (request.method == Method::Head && method == Method::Get)
+                })
+                .then_some(())
+        });
+
+        let allowed = self.allowed_methods(&request.uri.path);
+        if allowed.is_empty() || guarded.is_some() {
+            Target::NotFound
+        } else {
+            Target::Allowed(allowed)
+        }
+    }
+
+    /// Returns the states seen by the route, from its innermost nested router to this one.
+    fn states(&self, route: &Route) -> Vec<Arc<State>> {
+        route.state.iter().chain([&self.state]).cloned().collect()
+    }
+
+    /// Returns the methods allowed for the given path, in registration order.
+    ///
+    /// HEAD is implied by GET and OPTIONS by any route, since both are answered automatically.
+    pub fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let mut allowed = Vec::new();
+
+        self.tree.find(path, |routes, _| {
+            for &index in routes {
+                let method = self.routes[index].1.method;
+                if !allowed.contains(&method) {
+                    allowed.push(method);
+                }
+            }
+
+            None::<()>
+        });
+
+        if allowed.contains(&Method::Get) && !allowed.contains(&Method::Head) {
+            allowed.push(Method::Head);
+        }
+        if !allowed.is_empty() && !allowed.contains(&Method::Options) {
+            allowed.push(Method::Options);
+        }
+
+        allowed
+    }
+
+    /// Finds the first route for the method whose path matches and whose guards accept the
+    /// request, leaving its parameters in the request.
+    fn find(&self, request: &mut Request, method: Method) -> Option<&Route> {
+        let path = request.uri.path.clone();
+
+        self.tree.find(&path, |routes, captures| {
+            routes
+                .iter()
+                .map(|&index| &self.routes[index])
+                .filter(|(_, route)| route.method == method)
+                .find_map(|(pattern, route)| {
+                    request.params = pattern
+                        .params()
+                        .zip(captures)
+                        .map(|(name, value)| {
+                            let value = path::decode(value).unwrap_or_else(|| value.to_string());
+                            (name.to_owned(), value)
+                        })
+                        .collect();
+                    request.state = self.states(route);
+
+                    route
+                        .guards
+                        .iter()
+                        .all(|guard| guard(request))
+                        .then_some(route)
+                })
+        })
+    }
+}
+
+impl fmt::Display for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .routes()
+            .iter()
+            .map(|route| {
+                [
+                    route.host.map_or("*".to_string(), ToString::to_string),
+                    route.method.as_str().to_string(),
+                    route.path.to_string(),
+                    route.name.unwrap_or("-").to_string(),
+                    route.guards.to_string(),
+                    route.middleware.join(", "),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let header = ["HOST", "METHOD", "PATH", "NAME", "GUARDS", "MIDDLEWARE"].map(String::from);
+        let mut widths = header.clone().map(|column| column.len());
+        for row in &rows {
+            for (width, column) in widths.iter_mut().zip(row) {
+                *width = (*width).max(column.len());
+            }
+        }
+
+        for row in std::iter::once(&header).chain(&rows) {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(column, width)| format!("{column:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        extract::{Header, Named, Path},
+        http::{
+            request::Request,
+            response::{Response, ResponseBuilder},
+            Method,
+        },
+    };
+
+    use super::{
+        error::{BuildError, Conflict, RequestError, UrlError},
+        guard,
+        middleware::{from_fn, Middleware, Next},
+        Route, RouterBuilder,
+    };
+
+    fn echo_param(request: &Request) -> Response {
+        let body = request.param("id").map(String::as_str).unwrap_or("none");
+        ResponseBuilder::new().empty().with_body(body).build()
+    }
+
+    fn echo_path(request: &Request) -> Response {
+        let body = request.param("path").map(String::as_str).unwrap_or("none");
+        ResponseBuilder::new().empty().with_body(body).build()
+    }
+
+    #[test]
+    fn dispatch_params() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/users/:id", Method::Get, echo_param))
+            .with_route(Route::new("/static/*path", Method::Get, echo_path))
+            .build();
+
+        let mut request =
+            Request::try_from("GET /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(request.param("id"), Some(&"42".to_string()));
+        assert!(String::from(&response).ends_with("\r\n\r\n42"));
+
+        let mut request =
+            Request::try_from("GET /static/js/app.js HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).ends_with("\r\n\r\njs/app.js"));
+
+        let mut request =
+            Request::try_from("GET /posts/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn dispatch_method_not_allowed() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/users/:id", Method::Get, echo_param))
+            .with_route(Route::new("/users/:id", Method::Delete, echo_param))
+            .with_route(Route::new("/users/me", Method::Post, echo_param))
+            .build();
+
+        let mut request =
+            Request::try_from("PUT /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(
+            String::from(&response),
+            "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nAllow: GET, DELETE, HEAD, OPTIONS\r\n\r\n"
+        );
+
+        let mut request =
+            Request::try_from("PUT /users/me HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).contains("Allow: POST, GET, DELETE, HEAD, OPTIONS\r\n"));
+
+        let mut request =
+            Request::try_from("DELETE /users/me HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).ends_with("\r\n\r\nme"));
+    }
+
+    #[test]
+    fn dispatch_head_and_options() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/users/:id", Method::Get, echo_param))
+            .with_route(Route::new("/static/*path", Method::Post, echo_path))
+            .with_route(Route::new("/static/*path", Method::Options, echo_path))
+            .build();
+
+        let mut request =
+            Request::try_from("HEAD /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(
+            String::from(&response),
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n"
+        );
+
+        let mut request =
+            Request::try_from("OPTIONS /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(
+            String::from(&response),
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nAllow: GET, HEAD, OPTIONS\r\n\r\n"
+        );
+
+        let mut request =
+            Request::try_from("OPTIONS /static/app.js HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).ends_with("\r\n\r\napp.js"));
+
+        let mut request =
+            Request::try_from("HEAD /static/app.js HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).contains("Allow: POST, OPTIONS\r\n"));
+    }
+
+    #[test]
+    fn dispatch_closure() {
+        let visits = Arc::new(AtomicUsize::new(0));
+
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Get, {
+                let visits = visits.clone();
+                move |_request| {
+                    let count = visits.fetch_add(1, Ordering::SeqCst) + 1;
+                    ResponseBuilder::new()
+                        .empty()
+                        .with_body(&count.to_string())
+                        .build()
+                }
+            }))
+            .build();
+
+        for _ in 0..3 {
+            let mut request =
+                Request::try_from("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            router.dispatch(&mut request);
+        }
+
+        assert_eq!(visits.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn dispatch_state() {
+        struct Config {
+            greeting: String,
+        }
+
+        let router = RouterBuilder::new()
+            .with_state(Config {
+                greeting: "Hello".to_string(),
+            })
+            .with_route(Route::new("/users/:id", Method::Get, |request| {
+                let config = request.state::<Config>().unwrap();
+                let id = request.param("id").unwrap();
+
+                ResponseBuilder::new()
+                    .empty()
+                    .with_body(&format!("{}, {id}!", config.greeting))
+                    .build()
+            }))
+            .build();
+
+        let mut request =
+            Request::try_from("GET /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).ends_with("\r\n\r\nHello, 42!"));
+        assert!(request.state::<String>().is_none());
+    }
+
+    #[test]
+    fn dispatch_middleware() {
+        let router = RouterBuilder::new()
+            .with_middleware(from_fn(|request, next| {
+                let response = next.run(request);
+                response.with_header("X-Global", "1")
+            }))
+            .with_route(Route::new("/users/:id", Method::Get, echo_param))
+            .with_route(
+                Route::new("/admin", Method::Get, echo_param).with_middleware(from_fn(
+                    |request, next| match request.get_header("Authorization") {
+                        Some(_) => next.run(request),
+                        None => ResponseBuilder::new().with_status_code(401).build(),
+                    },
+                )),
+            )
+            .build();
+
+        let mut request =
+            Request::try_from("GET /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.get_header("X-Global"), Some(&"1".to_string()));
+        assert!(String::from(&response).ends_with("\r\n\r\n42"));
+
+        let mut request =
+            Request::try_from("GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 404);
+        assert_eq!(response.get_header("X-Global"), Some(&"1".to_string()));
+
+        let mut request =
+            Request::try_from("GET /admin HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 401);
+        assert_eq!(response.get_header("X-Global"), Some(&"1".to_string()));
+
+        let mut request = Request::try_from(
+            "GET /admin HTTP/1.1\r\nHost: localhost\r\nAuthorization: token\r\n\r\n",
+        )
+        .unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 200);
+    }
+
+    #[test]
+    fn dispatch_nested() {
+        let api = RouterBuilder::new()
+            .with_middleware(from_fn(|request, next| {
+                next.run(request).with_header("X-Api", "1")
+            }))
+            .with_route(Route::new("/", Method::Get, echo_param))
+            .with_route(Route::new("/users/:id", Method::Get, echo_param))
+            .build();
+
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/users/:id", Method::Get, echo_param))
+            .nest("/api/v1/", api)
+            .build();
+
+        let mut request =
+            Request::try_from("GET /api/v1/users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.get_header("X-Api"), Some(&"1".to_string()));
+        assert!(String::from(&response).ends_with("\r\n\r\n42"));
+
+        let mut request =
+            Request::try_from("GET /api/v1 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 200);
+
+        let mut request =
+            Request::try_from("GET /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.get_header("X-Api"), None);
+
+        let mut request =
+            Request::try_from("GET /api/v1/missing HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 404);
+        assert_eq!(response.get_header("X-Api"), None);
+    }
+
+    #[test]
+    fn nest_state_and_hosts() {
+        let greeting = |request: &Request| {
+            let greeting = request.state::<&str>().unwrap();
+            let count = request.state::<u32>().unwrap();
+            format!("{greeting} {count}")
+        };
+
+        let api = RouterBuilder::new()
+            .with_state("api")
+            .with_route(Route::new("/", Method::Get, greeting))
+            .build();
+
+        // The nested state may still be referenced
+        let mut request = Request::try_from("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        api.dispatch(&mut request);
+
+        let router = RouterBuilder::new()
+            .with_state("root")
+            .with_state(7u32)
+            .with_route(Route::new("/", Method::Get, greeting))
+            .nest("/api", api)
+            .build();
+
+        let mut request =
+            Request::try_from("GET /api HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        assert!(String::from(&router.dispatch(&mut request)).ends_with("\r\n\r\napi 7"));
+
+        let mut request = Request::try_from("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        assert!(String::from(&router.dispatch(&mut request)).ends_with("\r\n\r\nroot 7"));
+
+        let hosted = RouterBuilder::new()
+            .with_host("api.example.com", RouterBuilder::new().build())
+            .build();
+        let result = RouterBuilder::new().nest("/hosted", hosted).try_build();
+        assert_eq!(
+            result.err().map(|e| e.conflicts),
+            Some(vec![Conflict::NestedHosts {
+                prefix: "/hosted".to_string()
+            }])
+        );
+    }
+
+    #[test]
+    fn dispatch_fallback_and_errors() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/panic", Method::Get, |_request| -> Response {
+                panic!("database unavailable")
+            }))
+            .with_fallback(|request| {
+                ResponseBuilder::new()
+                    .not_found()
+                    .with_body(&format!("<h1>{} not found</h1>", request.uri.path))
+                    .build()
+            })
+            .with_error_handler(|error| match error {
+                RequestError::Parse(_) => ResponseBuilder::new()
+                    .bad_request()
+                    .with_body("{\"title\":\"Bad Request\"}")
+                    .build(),
+                RequestError::Panic(_) => ResponseBuilder::new()
+                    .internal_server_error()
+                    .with_body("{\"title\":\"Internal Server Error\"}")
+                    .build(),
+            })
+            .build();
+
+        let mut request =
+            Request::try_from("GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).ends_with("<h1>/missing not found</h1>"));
+
+        let mut request =
+            Request::try_from("GET /panic HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 500);
+        assert!(String::from(&response).ends_with("{\"title\":\"Internal Server Error\"}"));
+
+        let response = router.handle_error(&RequestError::Parse("Invalid method"));
+        assert_eq!(response.status_code(), 400);
+        assert!(!String::from(&response).contains("Invalid method"));
+
+        let router = RouterBuilder::new().build();
+        let response = router.handle_error(&RequestError::Parse("Invalid method"));
+        assert_eq!(
+            String::from(&response),
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 14\r\n\r\nInvalid method"
+        );
+    }
+
+    #[test]
+    fn url_for() {
+        let api = RouterBuilder::new()
+            .with_route(Route::new("/users/:id", Method::Get, echo_param).with_name("user_profile"))
+            .build();
+
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/static/*path", Method::Get, echo_path).with_name("static"))
+            .nest("/api", api)
+            .build();
+
+        assert_eq!(
+            router.url_for("user_profile", &[("id", "42")]),
+            Ok("/api/users/42".to_string())
+        );
+        assert_eq!(
+            router.url_for("static", &[("path", "css/my theme.css")]),
+            Ok("/static/css/my%20theme.css".to_string())
+        );
+        assert_eq!(
+            router.url_for("user_profile", &[]),
+            Err(UrlError::MissingParam("id".to_string()))
+        );
+        assert_eq!(
+            router.url_for("missing", &[]),
+            Err(UrlError::UnknownRoute("missing".to_string()))
+        );
+
+        let url = router
+            .url_for("user_profile", &[("id", "jane doe")])
+            .unwrap();
+        let mut request =
+            Request::try_from(format!("GET {url} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_str())
+                .unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(request.param("id"), Some(&"jane doe".to_string()));
+        assert!(String::from(&response).ends_with("\r\n\r\njane doe"));
+    }
+
+    #[test]
+    fn build_conflicts() {
+        let result = RouterBuilder::new()
+            .with_route(Route::new("/users/:id", Method::Get, echo_param).with_name("user"))
+            .with_route(Route::new("/users/:id", Method::Delete, echo_param).with_name("user"))
+            .with_route(Route::new("/users/:id", Method::Get, echo_param))
+            .with_route(Route::new("/users/:name", Method::Get, echo_param))
+            .with_route(Route::new("/users/me", Method::Get, echo_param))
+            .with_route(Route::new("users", Method::Get, echo_param))
+            .try_build();
+
+        let error = match result {
+            Ok(_) => panic!("Conflicting routes were accepted"),
+            Err(e) => e,
+        };
+
+        assert_eq!(
+            error,
+            BuildError {
+                conflicts: vec![
+                    Conflict::DuplicateName {
+                        name: "user".to_string()
+                    },
+                    Conflict::DuplicateRoute {
+                        method: Method::Get,
+                        path: "/users/:id".to_string()
+                    },
+                    Conflict::AmbiguousRoute {
+                        method: Method::Get,
+                        path: "/users/:name".to_string(),
+                        previous: "/users/:id".to_string()
+                    },
+                    Conflict::InvalidPattern {
+                        path: "users".to_string(),
+                        reason: "Route path must start with '/'"
+                    },
+                ]
+            }
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid routing table:\n  - Duplicate route name user\n  - Duplicate route GET /users/:id\n  - Route GET /users/:name is ambiguous with /users/:id\n  - Invalid route path users: Route path must start with '/'"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate route GET /")]
+    fn build_panics_on_conflict() {
+        RouterBuilder::new()
+            .with_route(Route::new("/", Method::Get, echo_param))
+            .with_route(Route::new("/", Method::Get, echo_param))
+            .build();
+    }
+
+    #[test]
+    fn dispatch_virtual_hosts() {
+        let echo_host = |name: &'static str| {
+            RouterBuilder::new()
+                .with_route(Route::new("/", Method::Get, move |_request| {
+                    ResponseBuilder::new().empty().with_body(name).build()
+                }))
+                .build()
+        };
+
+        let router = RouterBuilder::new()
+            .with_host("*.example.com", echo_host("wildcard"))
+            .with_host("api.example.com", echo_host("api"))
+            .with_route(Route::new("/", Method::Get, |_request| {
+                ResponseBuilder::new().empty().with_body("default").build()
+            }))
+            .build();
+
+        let body = |host: &str| {
+            let mut request =
+                Request::try_from(format!("GET / HTTP/1.1\r\nHost: {host}\r\n\r\n").as_str())
+                    .unwrap();
+            let response = String::from(&router.dispatch(&mut request));
+            response.split("\r\n\r\n").nth(1).unwrap().to_string()
+        };
+
+        assert_eq!(body("api.example.com"), "api");
+        assert_eq!(body("API.example.com:8080"), "api");
+        assert_eq!(body("www.example.com"), "wildcard");
+        assert_eq!(body("example.com"), "default");
+        assert_eq!(body("localhost"), "default");
+
+        let mut request = Request::try_from("GET / HTTP/1.1\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 400);
+
+        let mut request = Request::try_from("GET / HTTP/1.0\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 200);
+    }
+
+    #[test]
+    fn dispatch_guards() {
+        let reply = |body: &'static str| {
+            move |_request: &Request| ResponseBuilder::new().empty().with_body(body).build()
+        };
+
+        let router = RouterBuilder::new()
+            .with_route(
+                Route::new("/users/:id", Method::Get, reply("json"))
+                    .with_guard(guard::numeric("id"))
+                    .with_guard(guard::header("Accept", "application/json")),
+            )
+            .with_route(
+                Route::new("/users/:id", Method::Get, reply("by id"))
+                    .with_guard(guard::numeric("id")),
+            )
+            .with_route(Route::new("/users/:name", Method::Get, reply("by name")))
+            .with_route(
+                Route::new("/report", Method::Get, reply("csv"))
+                    .with_guard(guard::query("format", "csv")),
+            )
+            .with_route(
+                Route::new("/broken", Method::Get, reply("unreachable"))
+                    .with_guard(|_request: &Request| panic!("guard failed")),
+            )
+            .try_build()
+            .unwrap();
+
+        let body = |request: &str| {
+            let mut request = Request::try_from(request).unwrap();
+            let response = String::from(&router.dispatch(&mut request));
+            response.split("\r\n\r\n").nth(1).unwrap().to_string()
+        };
+
+        assert_eq!(
+            body("GET /users/42 HTTP/1.1\r\nHost: localhost\r\nAccept: application/json\r\n\r\n"),
+            "json"
+        );
+        assert_eq!(
+            body("GET /users/42 HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            "by id"
+        );
+        assert_eq!(
+            body("GET /users/jane HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            "by name"
+        );
+        assert_eq!(
+            body("GET /report?format=csv HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            "csv"
+        );
+
+        let mut request =
+            Request::try_from("GET /report?format=pdf HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 404);
+
+        let mut request =
+            Request::try_from("GET /broken HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 500);
+    }
+
+    #[test]
+    fn dispatch_typed() {
+        struct Limit;
+
+        impl Named for Limit {
+            const NAME: &'static str = "X-Limit";
+        }
+
+        let router = RouterBuilder::new()
+            .with_route(Route::typed(
+                "/users/:user/posts/:post",
+                Method::Get,
+                |Path((user, post)): Path<(u32, u32)>, limit: Option<Header<Limit, u8>>| {
+                    let limit = limit.map(|Header(limit, _)| limit).unwrap_or(10);
+                    ResponseBuilder::new()
+                        .empty()
+                        .with_body(&format!("{user}/{post}/{limit}"))
+                        .build()
+                },
+            ))
+            .with_route(Route::typed("/health", Method::Get, || {
+                ResponseBuilder::new().empty().build()
+            }))
+            .build();
+
+        let mut request = Request::try_from(
+            "GET /users/1/posts/2 HTTP/1.1\r\nHost: localhost\r\nX-Limit: 5\r\n\r\n",
+        )
+        .unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).ends_with("\r\n\r\n1/2/5"));
+
+        let mut request =
+            Request::try_from("GET /users/1/posts/2 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert!(String::from(&response).ends_with("\r\n\r\n1/2/10"));
+
+        let mut request = Request::try_from(
+            "GET /users/1/posts/2 HTTP/1.1\r\nHost: localhost\r\nX-Limit: abc\r\n\r\n",
+        )
+        .unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(
+            String::from(&response),
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 53\r\n\r\nInvalid header X-Limit: invalid digit found in string"
+        );
+
+        let mut request =
+            Request::try_from("GET /users/1/posts/latest HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(
+            String::from(&response),
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 58\r\n\r\nInvalid path parameter post: invalid digit found in string"
+        );
+
+        let mut request =
+            Request::try_from("GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(response.status_code(), 200);
+    }
+
+    #[test]
+    fn dispatch_fallible() {
+        fn find_user(id: &str) -> Result<&'static str, (u16, String)> {
+            match id {
+                "1" => Ok("alice"),
+                _ => Err((404, format!("No user {id}"))),
+            }
+        }
+
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/users/:id", Method::Get, |request| {
+                let user = find_user(request.param("id").unwrap())?;
+                Ok::<_, (u16, String)>(format!("Hello, {user}!"))
+            }))
+            .with_route(Route::typed("/teapot", Method::Get, || {
+                (418, "I'm a teapot")
+            }))
+            .build();
+
+        let body = |request: &str| {
+            let mut request = Request::try_from(request).unwrap();
+            String::from(&router.dispatch(&mut request))
+        };
+
+        assert!(body("GET /users/1 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .ends_with("\r\n\r\nHello, alice!"));
+        assert!(body("GET /users/2 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .starts_with("HTTP/1.1 404 Not Found"));
+        assert!(
+            body("GET /teapot HTTP/1.1\r\nHost: localhost\r\n\r\n").starts_with("HTTP/1.1 418 ")
+        );
+    }
+
+    #[test]
+    fn list_routes() {
+        struct Auth;
+
+        impl Middleware for Auth {
+            fn handle(&self, request: &Request, next: Next<'_>) -> Response {
+                next.run(request)
+            }
+
+            fn name(&self) -> &str {
+                "auth"
+            }
+        }
+
+        let router = RouterBuilder::new()
+            .with_middleware(Auth)
+            .with_route(Route::new("/users/:id", Method::Get, echo_param).with_name("user"))
+            .with_host(
+                "*.example.com",
+                RouterBuilder::new()
+                    .with_route(
+                        Route::new("/admin", Method::Post, echo_param)
+                            .with_guard(guard::query("token", "1")),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let routes = router.routes();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(
+            routes[0],
+            super::RouteInfo {
+                host: None,
+                method: Method::Get,
+                path: "/users/:id",
+                name: Some("user"),
+                middleware: vec!["auth"],
+                guards: 0,
+            }
+        );
+        assert_eq!(
+            routes[1].host.map(ToString::to_string).as_deref(),
+            Some("*.example.com")
+        );
+        assert_eq!(routes[1].guards, 1);
+        assert!(routes[1].middleware.is_empty());
+
+        assert_eq!(
+            router.to_string(),
+            "HOST           METHOD  PATH        NAME  GUARDS  MIDDLEWARE\n\
+             *              GET     /users/:id  user  0       auth\n\
+             *.example.com  POST    /admin      -     1\n"
+        );
+    }
+}
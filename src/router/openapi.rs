@@ -0,0 +1,419 @@
+//! OpenAPI 3 documents generated from the routing table, see [`Route::with_operation`] and
+//! [`Router::openapi`](super::Router::openapi).
+
+use crate::http::{response, Method};
+
+use super::{
+    path::{Pattern, Segment},
+    Route,
+};
+
+/// The title and version of the documented API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Info {
+    pub title: String,
+    pub version: String,
+}
+
+impl Info {
+    pub fn new(title: &str, version: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            version: version.to_string(),
+        }
+    }
+}
+
+/// The schema of a parameter or JSON body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Array(Box<Schema>),
+    /// An object whose listed properties are all required.
+    Object(Vec<(String, Schema)>),
+}
+
+/// Where a parameter is read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    Path,
+    Query,
+    Header,
+    Cookie,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub location: Location,
+    pub required: bool,
+    pub schema: Schema,
+}
+
+/// The metadata of a route documented in the OpenAPI document.
+///
+/// Path parameters missing from `parameters` are documented as required strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Operation {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub parameters: Vec<Parameter>,
+    pub request_body: Option<Schema>,
+    /// The documented responses as status code, description and JSON body schema.
+    pub responses: Vec<(u16, String, Option<Schema>)>,
+}
+
+impl Operation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_summary(self, summary: &str) -> Self {
+        Self {
+            summary: Some(summary.to_string()),
+            ..self
+        }
+    }
+
+    pub fn with_description(self, description: &str) -> Self {
+        Self {
+            description: Some(description.to_string()),
+            ..self
+        }
+    }
+
+    pub fn with_parameter(
+        mut self,
+        name: &str,
+        location: Location,
+        required: bool,
+        schema: Schema,
+    ) -> Self {
+        self.parameters.push(Parameter {
+            name: name.to_string(),
+            location,
+            required: required || location == Location::Path,
+            schema,
+        });
+        self
+    }
+
+    /// Documents a JSON request body.
+    pub fn with_json_body(self, schema: Schema) -> Self {
+        Self {
+            request_body: Some(schema),
+            ..self
+        }
+    }
+
+    /// Documents a response without body.
+    pub fn with_response(mut self, status_code: u16, description: &str) -> Self {
+        self.responses
+            .push((status_code, description.to_string(), None));
+        self
+    }
+
+    /// Documents a response with a JSON body.
+    pub fn with_json_response(
+        mut self,
+        status_code: u16,
+        description: &str,
+        schema: Schema,
+    ) -> Self {
+        self.responses
+            .push((status_code, description.to_string(), Some(schema)));
+        self
+    }
+}
+
+impl Route {
+    /// Documents the route in the OpenAPI document generated by the router.
+    pub fn with_operation(self, operation: Operation) -> Self {
+        Self {
+            operation: Some(operation),
+            ..self
+        }
+    }
+}
+
+/// Generates the OpenAPI 3 document describing the routes, as JSON.
+///
+/// Routes with an invalid pattern or a CONNECT method, which OpenAPI cannot describe, are skipped.
+pub(crate) fn document<'a>(info: &Info, routes: impl IntoIterator<Item = &'a Route>) -> String {
+    let mut paths: Vec<(String, Vec<String>)> = Vec::new();
+
+    for route in routes {
+        let Ok(pattern) = Pattern::try_from(route.path.as_str()) else {
+            continue;
+        };
+        if route.method == Method::Connect {
+            continue;
+        }
+
+        let path = path(&pattern);
+        let operation = format!(
+            "{}:{}",
+            string(&route.method.as_str().to_ascii_lowercase()),
+            operation(route, &pattern)
+        );
+
+        match paths.iter_mut().find(|(existing, _)| *existing == path) {
+            Some((_, operations)) => operations.push(operation),
+            None => paths.push((path, vec![operation])),
+        }
+    }
+
+    let paths = paths
+        .iter()
+        .map(|(path, operations)| format!("{}:{{{}}}", string(path), operations.join(",")))
+        .collect::<Vec<_>>();
+
+    format!(
+        r#"{{"openapi":"3.0.3","info":{{"title":{},"version":{}}},"paths":{{{}}}}}"#,
+        string(&info.title),
+        string(&info.version),
+        paths.join(",")
+    )
+}
+
+/// Converts a pattern to an OpenAPI path template, e.g. `/users/{id}` for `/users/:id`.
+fn path(pattern: &Pattern) -> String {
+    if pattern.segments.is_empty() {
+        return "/".to_string();
+    }
+
+    pattern
+        .segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Static(value) => format!("/{value}"),
+            Segment::Param(name) | Segment::Wildcard(name) => format!("/{{{name}}}"),
+        })
+        .collect()
+}
+
+fn operation(route: &Route, pattern: &Pattern) -> String {
+    let default = Operation::default();
+    let operation = route.operation.as_ref().unwrap_or(&default);
+    let mut fields = Vec::new();
+
+    if let Some(name) = &route.name {
+        fields.push(format!(r#""operationId":{}"#, string(name)));
+    }
+    if let Some(summary) = &operation.summary {
+        fields.push(format!(r#""summary":{}"#, string(summary)));
+    }
+    if let Some(description) = &operation.description {
+        fields.push(format!(r#""description":{}"#, string(description)));
+    }
+
+    let undocumented = pattern.params().filter(|name| {
+        !operation
+            .parameters
+            .iter()
+            .any(|param| param.location == Location::Path && param.name == *name)
+    });
+    let parameters = undocumented
+        .map(|name| parameter(name, Location::Path, true, &Schema::String))
+        .chain(
+            operation
+                .parameters
+                .iter()
+                .map(|param| parameter(&param.name, param.location, param.required, &param.schema)),
+        )
+        .collect::<Vec<_>>();
+    if !parameters.is_empty() {
+        fields.push(format!(r#""parameters":[{}]"#, parameters.join(",")));
+    }
+
+    if let Some(body) = &operation.request_body {
+        fields.push(format!(
+            r#""requestBody":{{"required":true,"content":{}}}"#,
+            content(body)
+        ));
+    }
+
+    let responses = match operation.responses.is_empty() {
+        true => vec![format!(r#""200":{{"description":"OK"}}"#)],
+        false => operation
+            .responses
+            .iter()
+            .map(|(status_code, description, schema)| {
+                let description = match description.is_empty() {
+                    true => response::reason_phrase(*status_code),
+                    false => description,
+                };
+
+                match schema {
+                    Some(schema) => format!(
+                        r#""{status_code}":{{"description":{},"content":{}}}"#,
+                        string(description),
+                        content(schema)
+                    ),
+                    None => format!(
+                        r#""{status_code}":{{"description":{}}}"#,
+                        string(description)
+                    ),
+                }
+            })
+            .collect(),
+    };
+    fields.push(format!(r#""responses":{{{}}}"#, responses.join(",")));
+
+    format!("{{{}}}", fields.join(","))
+}
+
+fn parameter(name: &str, location: Location, required: bool, schema: &Schema) -> String {
+    let location = match location {
+        Location::Path => "path",
+        Location::Query => "query",
+        Location::Header => "header",
+        Location::Cookie => "cookie",
+    };
+
+    format!(
+        r#"{{"name":{},"in":"{location}","required":{required},"schema":{}}}"#,
+        string(name),
+        schema.to_json()
+    )
+}
+
+fn content(schema: &Schema) -> String {
+    format!(
+        r#"{{"application/json":{{"schema":{}}}}}"#,
+        schema.to_json()
+    )
+}
+
+impl Schema {
+    fn to_json(&self) -> String {
+        match self {
+            Schema::String => r#"{"type":"string"}"#.to_string(),
+            Schema::Integer => r#"{"type":"integer"}"#.to_string(),
+            Schema::Number => r#"{"type":"number"}"#.to_string(),
+            Schema::Boolean => r#"{"type":"boolean"}"#.to_string(),
+            Schema::Array(items) => format!(r#"{{"type":"array","items":{}}}"#, items.to_json()),
+            Schema::Object(properties) if properties.is_empty() => {
+                r#"{"type":"object"}"#.to_string()
+            }
+            Schema::Object(properties) => {
+                let names = properties.iter().map(|(name, _)| string(name));
+                let properties = properties
+                    .iter()
+                    .map(|(name, schema)| format!("{}:{}", string(name), schema.to_json()));
+
+                format!(
+                    r#"{{"type":"object","properties":{{{}}},"required":[{}]}}"#,
+                    properties.collect::<Vec<_>>().join(","),
+                    names.collect::<Vec<_>>().join(",")
+                )
+            }
+        }
+    }
+}
+
+/// Quotes and escapes a JSON string.
+fn string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{
+        request::Request,
+        response::{Response, ResponseBuilder},
+        Method,
+    };
+
+    use super::super::{error::Conflict, Route, RouterBuilder};
+    use super::{Info, Location, Operation, Schema};
+
+    fn handler(_request: &Request) -> Response {
+        ResponseBuilder::new().empty().build()
+    }
+
+    #[test]
+    fn generate_document() {
+        let router = RouterBuilder::new()
+            .with_route(Route::new("/", Method::Get, handler))
+            .with_route(
+                Route::new("/users/:id", Method::Get, handler)
+                    .with_name("user")
+                    .with_operation(
+                        Operation::new()
+                            .with_summary("Fetch a \"user\"")
+                            .with_parameter("fields", Location::Query, false, Schema::String)
+                            .with_json_response(
+                                200,
+                                "The user",
+                                Schema::Object(vec![
+                                    ("id".to_string(), Schema::Integer),
+                                    ("tags".to_string(), Schema::Array(Box::new(Schema::String))),
+                                ]),
+                            )
+                            .with_response(404, ""),
+                    ),
+            )
+            .with_route(
+                Route::new("/users/:id", Method::Put, handler)
+                    .with_operation(Operation::new().with_json_body(Schema::Object(vec![]))),
+            )
+            .with_openapi("/openapi.json", Info::new("Users", "1.0"))
+            .build();
+
+        let expected = concat!(
+            r#"{"openapi":"3.0.3","info":{"title":"Users","version":"1.0"},"paths":{"#,
+            r#""/":{"get":{"responses":{"200":{"description":"OK"}}}},"#,
+            r#""/users/{id}":{"get":{"operationId":"user","summary":"Fetch a \"user\"","#,
+            r#""parameters":[{"name":"id","in":"path","required":true,"schema":{"type":"string"}},"#,
+            r#"{"name":"fields","in":"query","required":false,"schema":{"type":"string"}}],"#,
+            r#""responses":{"200":{"description":"The user","content":{"application/json":"#,
+            r#"{"schema":{"type":"object","properties":{"id":{"type":"integer"},"#,
+            r#""tags":{"type":"array","items":{"type":"string"}}},"required":["id","tags"]}}}},"#,
+            r#""404":{"description":"Not Found"}}},"#,
+            r#""put":{"parameters":[{"name":"id","in":"path","required":true,"schema":{"type":"string"}}],"#,
+            r#""requestBody":{"required":true,"content":{"application/json":{"schema":{"type":"object"}}}},"#,
+            r#""responses":{"200":{"description":"OK"}}}},"#,
+            r#""/openapi.json":{"get":{"responses":{"200":{"description":"OK"}}}}}}"#,
+        );
+
+        assert_eq!(router.openapi(&Info::new("Users", "1.0")), expected);
+
+        let mut request =
+            Request::try_from("GET /openapi.json HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let response = router.dispatch(&mut request);
+        assert_eq!(
+            response.get_header("Content-Type"),
+            Some(&"application/json".to_string())
+        );
+        assert!(String::from(&response).ends_with(expected));
+
+        // The served document would describe the paths without the prefix
+        let result = RouterBuilder::new().nest("/v1", router).try_build();
+        assert_eq!(
+            result.err().map(|e| e.conflicts),
+            Some(vec![Conflict::NestedOpenApi {
+                prefix: "/v1".to_string()
+            }])
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use super::error::UrlError;
+
+/// A single segment of a route pattern.
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    /// Matches a segment equal to the given string, e.g. `users`.
+    Static(String),
+    /// Matches any non empty segment and captures it under the given name, e.g. `:id`.
+    Param(String),
+    /// Matches the remainder of the path and captures it under the given name, e.g. `*rest`.
+    Wildcard(String),
+}
+
+/// A parsed route path such as `/users/:id` or `/static/*rest`.
+#[derive(Debug, PartialEq)]
+pub struct Pattern {
+    pub segments: Vec<Segment>,
+}
+
+impl Pattern {
+    /// Returns the names of the parameters and wildcard captured by the pattern, in order.
+    pub fn params(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Static(_) => None,
+            Segment::Param(name) | Segment::Wildcard(name) => Some(name.as_str()),
+        })
+    }
+
+    /// Returns the pattern with parameter and wildcard names erased, e.g. `/users/:/files/*` for
+    /// `/users/:id/files/*path`. Two patterns with the same shape match exactly the same paths.
+    pub fn shape(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Static(value) => format!("/{value}"),
+                Segment::Param(_) => "/:".to_string(),
+                Segment::Wildcard(_) => "/*".to_string(),
+            })
+            .collect()
+    }
+
+    /// Builds a path from the pattern, percent-encoding the given parameter values.
+    ///
+    /// Slashes are kept in wildcard values so they can span several segments.
+    pub fn format(&self, params: &[(&str, &str)]) -> Result<String, UrlError> {
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| UrlError::MissingParam(name.to_string()))
+        };
+
+        let mut path = String::new();
+
+        for segment in &self.segments {
+            path.push('/');
+
+            match segment {
+                Segment::Static(value) => path.push_str(value),
+                Segment::Param(name) => {
+                    let value = param(name)?;
+                    if value.is_empty() {
+                        return Err(UrlError::MissingParam(name.to_string()));
+                    }
+
+                    path.push_str(&encode(value));
+                }
+                Segment::Wildcard(name) => {
+                    let value = param(name)?.split('/').map(encode);
+                    path.push_str(&value.collect::<Vec<_>>().join("/"));
+                }
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+impl TryFrom<&str> for Pattern {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if !value.starts_with('/') {
+            return Err("Route path must start with '/'");
+        }
+
+        let mut segments = Vec::new();
+        let mut tokens = split(value).peekable();
+
+        while let Some(token) = tokens.next() {
+            let segment = if let Some(name) = token.strip_prefix(':') {
+                if name.is_empty() {
+                    return Err("Empty parameter name");
+                }
+                if segments.contains(&Segment::Param(name.to_owned())) {
+                    return Err("Duplicate parameter name");
+                }
+
+                Segment::Param(name.to_owned())
+            } else if let Some(name) = token.strip_prefix('*') {
+                if name.is_empty() {
+                    return Err("Empty wildcard name");
+                }
+                if segments.contains(&Segment::Param(name.to_owned())) {
+                    return Err("Duplicate parameter name");
+                }
+                if tokens.peek().is_some() {
+                    return Err("Wildcard must be the last segment");
+                }
+
+                Segment::Wildcard(name.to_owned())
+            } else {
+                Segment::Static(token.to_owned())
+            };
+
+            segments.push(segment);
+        }
+
+        Ok(Self { segments })
+    }
+}
+
+/// Percent-encodes every byte of the value which is not an unreserved character as defined in
+/// [RFC 3986](https://datatracker.ietf.org/doc/html/rfc3986#section-2.3).
+pub fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Decodes the percent-encoded bytes of the value, returning `None` if an escape is malformed or
+/// the result is not valid UTF-8.
+pub fn decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut tokens = value.bytes();
+
+    while let Some(byte) = tokens.next() {
+        match byte {
+            b'%' => {
+                let high = (tokens.next()? as char).to_digit(16)?;
+                let low = (tokens.next()? as char).to_digit(16)?;
+                bytes.push((high * 16 + low) as u8);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Splits a path into its segments, ignoring the leading slash.
+fn split(path: &str) -> std::str::Split<'_, char> {
+    path.strip_prefix('/').unwrap_or(path).split('/')
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode, Pattern, Segment};
+    use crate::router::error::UrlError;
+
+    #[test]
+    fn parse_pattern() {
+        let pattern = Pattern::try_from("/users/:id/*rest").unwrap();
+        assert_eq!(
+            pattern.segments,
+            vec![
+                Segment::Static("users".to_string()),
+                Segment::Param("id".to_string()),
+                Segment::Wildcard("rest".to_string()),
+            ]
+        );
+
+        assert_eq!(pattern.params().collect::<Vec<_>>(), vec!["id", "rest"]);
+        assert_eq!(pattern.shape(), "/users/:/*");
+
+        assert!(Pattern::try_from("users").is_err());
+        assert!(Pattern::try_from("/users/:").is_err());
+        assert!(Pattern::try_from("/static/*rest/file").is_err());
+        assert!(Pattern::try_from("/users/:id/posts/:id").is_err());
+    }
+
+    #[test]
+    fn format_pattern() {
+        let pattern = Pattern::try_from("/users/:id/files/*path").unwrap();
+
+        assert_eq!(
+            pattern.format(&[("id", "john doe"), ("path", "a/b c.txt")]),
+            Ok("/users/john%20doe/files/a/b%20c.txt".to_string())
+        );
+        assert_eq!(
+            pattern.format(&[("path", "a.txt")]),
+            Err(UrlError::MissingParam("id".to_string()))
+        );
+
+        let pattern = Pattern::try_from("/").unwrap();
+        assert_eq!(pattern.format(&[]), Ok("/".to_string()));
+    }
+
+    #[test]
+    fn percent_encoding() {
+        assert_eq!(encode("a b/ü~"), "a%20b%2F%C3%BC~");
+        assert_eq!(decode("a%20b%2F%C3%BC~"), Some("a b/ü~".to_string()));
+        assert_eq!(decode("100%"), None);
+        assert_eq!(decode("%zz"), None);
+    }
+}
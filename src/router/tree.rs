@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use super::path::{Pattern, Segment};
+
+/// A prefix tree of route patterns, matching a path in time proportional to its number of segments.
+///
+/// Children are tried in a fixed priority order: static segments first, then parameters and
+/// finally wildcards, backtracking to the next candidate when a branch yields no match.
+#[derive(Default)]
+pub struct Tree {
+    root: Node,
+}
+
+#[derive(Default)]
+struct Node {
+    statics: HashMap<String, Node>,
+    param: Option<Box<Node>>,
+    wildcard: Option<Box<Node>>,
+    routes: Vec<usize>,
+}
+
+impl Tree {
+    /// Registers the route identified by `route` under the given pattern.
+    pub fn insert(&mut self, pattern: &Pattern, route: usize) {
+        let mut node = &mut self.root;
+
+        for segment in &pattern.segments {
+            node = match segment {
+                Segment::Static(value) => node.statics.entry(value.to_owned()).or_default(),
+                Segment::Param(_) => node.param.get_or_insert_with(Default::default),
+                Segment::Wildcard(_) => node.wildcard.get_or_insert_with(Default::default),
+            };
+        }
+
+        node.routes.push(route);
+    }
+
+    /// Walks the tree in priority order, calling `accept` with the routes and captured values of
+    /// every node matching the path until it returns a value.
+    pub fn find<'p, T>(
+        &self,
+        path: &'p str,
+        mut accept: impl FnMut(&[usize], &[&'p str]) -> Option<T>,
+    ) -> Option<T> {
+        let segments = segments(path);
+        let mut captures = Vec::new();
+
+        self.root.find(path, &segments, &mut captures, &mut accept)
+    }
+}
+
+impl Node {
+    fn find<'p, T>(
+        &self,
+        path: &'p str,
+        segments: &[(usize, &'p str)],
+        captures: &mut Vec<&'p str>,
+        accept: &mut impl FnMut(&[usize], &[&'p str]) -> Option<T>,
+    ) -> Option<T> {
+        match segments.split_first() {
+            Some(((_, segment), rest)) => {
+                if let Some(child) = self.statics.get(*segment) {
+                    if let Some(found) = child.find(path, rest, captures, accept) {
+                        return Some(found);
+                    }
+                }
+
+                if let Some(child) = self.param.as_ref().filter(|_| !segment.is_empty()) {
+                    captures.push(segment);
+                    if let Some(found) = child.find(path, rest, captures, accept) {
+                        return Some(found);
+                    }
+                    captures.pop();
+                }
+            }
+            None => {
+                if !self.routes.is_empty() {
+                    if let Some(found) = accept(&self.routes, captures) {
+                        return Some(found);
+                    }
+                }
+            }
+        }
+
+        if let Some(child) = &self.wildcard {
+            let remainder = match segments.first() {
+                Some((start, _)) => &path[*start..],
+                None => "",
+            };
+
+            captures.push(remainder);
+            if let Some(found) = accept(&child.routes, captures) {
+                return Some(found);
+            }
+            captures.pop();
+        }
+
+        None
+    }
+}
+
+/// Splits a path into its segments along with their byte offset, ignoring the leading slash.
+fn segments(path: &str) -> Vec<(usize, &str)> {
+    let start = usize::from(path.starts_with('/'));
+    let mut offset = start;
+
+    path[start..]
+        .split('/')
+        .map(|segment| {
+            let item = (offset, segment);
+            offset += segment.len() + 1;
+            item
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::Tree;
+    use crate::router::path::Pattern;
+
+    fn tree(paths: &[&str]) -> Tree {
+        let mut tree = Tree::default();
+        for (index, path) in paths.iter().enumerate() {
+            tree.insert(&Pattern::try_from(*path).unwrap(), index);
+        }
+        tree
+    }
+
+    fn find(tree: &Tree, path: &str) -> Option<(usize, Vec<String>)> {
+        tree.find(path, |routes, captures| {
+            Some((
+                routes[0],
+                captures.iter().map(|capture| capture.to_string()).collect(),
+            ))
+        })
+    }
+
+    #[test]
+    fn match_paths() {
+        let tree = tree(&["/", "/users/:id", "/static/*path"]);
+
+        assert_eq!(find(&tree, "/"), Some((0, vec![])));
+        assert_eq!(find(&tree, "/index"), None);
+
+        assert_eq!(find(&tree, "/users/42"), Some((1, vec!["42".to_string()])));
+        assert_eq!(find(&tree, "/users"), None);
+        assert_eq!(find(&tree, "/users/"), None);
+        assert_eq!(find(&tree, "/users/42/posts"), None);
+
+        assert_eq!(
+            find(&tree, "/static/css/main.css"),
+            Some((2, vec!["css/main.css".to_string()]))
+        );
+        assert_eq!(find(&tree, "/static"), Some((2, vec!["".to_string()])));
+        assert_eq!(find(&tree, "/public/main.css"), None);
+    }
+
+    #[test]
+    fn match_priority() {
+        let tree = tree(&[
+            "/files/*path",
+            "/files/:name",
+            "/files/index",
+            "/files/:name/raw",
+        ]);
+
+        assert_eq!(find(&tree, "/files/index"), Some((2, vec![])));
+        assert_eq!(
+            find(&tree, "/files/a.txt"),
+            Some((1, vec!["a.txt".to_string()]))
+        );
+        assert_eq!(
+            find(&tree, "/files/index/raw"),
+            Some((3, vec!["index".to_string()]))
+        );
+        assert_eq!(
+            find(&tree, "/files/a/b/c"),
+            Some((0, vec!["a/b/c".to_string()]))
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+};
+
+/// Application state shared by every handler of a [`Router`](crate::router::Router), holding at
+/// most one value per type.
+#[derive(Default)]
+pub struct State {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl State {
+    /// Stores the value, replacing any previous value of the same type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("State")
+            .field("values", &self.values.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::State;
+
+    #[test]
+    fn insert_and_get() {
+        let mut state = State::default();
+        state.insert(42u32);
+        state.insert("name".to_string());
+        state.insert(7u32);
+
+        assert_eq!(state.get::<u32>(), Some(&7));
+        assert_eq!(state.get::<String>(), Some(&"name".to_string()));
+        assert_eq!(state.get::<i64>(), None);
+    }
+}